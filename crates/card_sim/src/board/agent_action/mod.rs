@@ -12,10 +12,12 @@ use bevy::{
 };
 use bevy_replicon::{
     bincode,
-    prelude::{ChannelKind, ServerEventAppExt},
+    prelude::{ChannelKind, SendMode, ServerEventAppExt, ToClients},
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
+use crate::agent_client_id;
+
 pub(crate) fn agent_action_plugin(app: &mut App) {
     app.init_resource::<AgentActionRegistry>();
 
@@ -40,6 +42,7 @@ fn client_agent_action_packet_handler(
             packet.state.board,
             packet.state.agent_action_id,
             &packet.state.data,
+            &packet.state.entities,
             &mut commands,
         ) {
             eprintln!("Failed to handle agent action packet: {:?}", e);
@@ -50,7 +53,15 @@ fn client_agent_action_packet_handler(
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct AgentActionId(u32);
 
-pub trait AgentAction {}
+pub trait AgentAction {
+    /// The entities referenced by the action, they are sent next to its data so the client can map them
+    fn entities(&self) -> Vec<Entity> {
+        Vec::new()
+    }
+
+    /// Receive the entities returned by [`AgentAction::entities`] once mapped to the client entities
+    fn set_entities(&mut self, _entities: Vec<Entity>) {}
+}
 
 pub struct AgentActionInput<T> {
     pub agent: Entity,
@@ -64,6 +75,7 @@ pub struct AgentActionState {
     agent: Entity,
     board: Entity,
     data: Vec<u8>,
+    entities: Vec<Entity>,
     agent_action_id: AgentActionId,
 }
 
@@ -86,6 +98,7 @@ impl AgentActionPacket {
                 agent,
                 board,
                 data,
+                entities: agent_action.entities(),
                 agent_action_id,
             },
         }
@@ -96,6 +109,9 @@ impl MapEntities for AgentActionPacket {
     fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
         self.state.agent = entity_mapper.map_entity(self.state.agent);
         self.state.board = entity_mapper.map_entity(self.state.board);
+        for entity in self.state.entities.iter_mut() {
+            *entity = entity_mapper.map_entity(*entity);
+        }
     }
 }
 
@@ -113,6 +129,7 @@ impl AgentActionPacket {
                 agent,
                 board,
                 data,
+                entities: agent_action.entities(),
                 agent_action_id,
             },
         }
@@ -131,6 +148,7 @@ trait AgentActionFactory {
         board: Entity,
         agent_action_id: AgentActionId,
         data: &[u8],
+        entities: &[Entity],
         commands: &mut Commands,
         system_id: Entity,
     ) -> Result<(), Box<dyn std::error::Error>>;
@@ -157,16 +175,20 @@ where
         board: Entity,
         agent_action_id: AgentActionId,
         data: &[u8],
+        entities: &[Entity],
         commands: &mut Commands,
         system_id: Entity,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut data = bincode::deserialize::<T>(data)?;
+        data.set_entities(entities.to_vec());
+
         commands.run_system_with_input(
             SystemId::<AgentActionInput<T>>::from_entity(system_id),
             AgentActionInput {
                 agent,
                 board,
                 agent_action_id,
-                data: bincode::deserialize::<T>(data)?,
+                data,
             },
         );
 
@@ -225,6 +247,7 @@ impl AgentActionRegistry {
         board: Entity,
         id: AgentActionId,
         data: &[u8],
+        entities: &[Entity],
         commands: &mut Commands,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let (factory, system_id) = self
@@ -232,6 +255,33 @@ impl AgentActionRegistry {
             .get(&id)
             .ok_or_else(|| -> Box<dyn std::error::Error> { "AgentAction not registered".into() })?;
 
-        factory.run(agent, board, id, data, commands, *system_id)
+        factory.run(agent, board, id, data, entities, commands, *system_id)
     }
 }
+
+/// Send the action to the client controlling the agent, returns false when the agent has no client to ask
+pub(crate) fn send_agent_action<
+    T: AgentAction + 'static + Send + Sync + Serialize + DeserializeOwned,
+>(
+    world: &mut World,
+    board: Entity,
+    agent: Entity,
+    agent_action: T,
+) -> bool {
+    let Some(client_id) = agent_client_id(world, agent) else {
+        return false;
+    };
+    let Some(agent_action_id) = world
+        .get_resource::<AgentActionRegistry>()
+        .and_then(|registry| registry.get_action_id::<T>().copied())
+    else {
+        error!("Tried to send an agent action that is not registered");
+        return false;
+    };
+
+    world.send_event(ToClients {
+        mode: SendMode::Direct(client_id),
+        event: AgentActionPacket::new(agent, board, agent_action, agent_action_id),
+    });
+    true
+}
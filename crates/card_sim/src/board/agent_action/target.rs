@@ -1,50 +1,220 @@
-use bevy::prelude::*;
+use bevy::{ecs::world::Command, prelude::*};
 use bevy_mod_picking::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::{AgentOwned, Board, BoardSlot};
+use crate::TargetResponsePacket;
 
 use super::{AgentAction, AgentActionInput};
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct TargetAgentAction {}
+/// Ask the agent to choose the targets of a group of an effect waiting on the server
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TargetAgentAction {
+    /// Identify the pending effect on the server, sent back with the chosen targets
+    pub request: u32,
+    pub group: String,
+    /// Sent as the entities of the action so they are mapped on the client
+    #[serde(skip)]
+    pub candidates: Vec<Entity>,
+    pub min: usize,
+    pub max: usize,
+}
+
+impl TargetAgentAction {
+    pub fn new(
+        request: u32,
+        group: String,
+        candidates: Vec<Entity>,
+        min: usize,
+        max: usize,
+    ) -> Self {
+        Self {
+            request,
+            group,
+            candidates,
+            min,
+            max,
+        }
+    }
+}
+
+impl AgentAction for TargetAgentAction {
+    fn entities(&self) -> Vec<Entity> {
+        self.candidates.clone()
+    }
+
+    fn set_entities(&mut self, entities: Vec<Entity>) {
+        self.candidates = entities;
+    }
+}
+
+/// The targets the client is choosing, only one selection can be in progress
+#[derive(Resource, Debug)]
+pub struct TargetSelection {
+    pub board: Entity,
+    pub request: TargetAgentAction,
+    pub selected: Vec<Entity>,
+}
+
+impl TargetSelection {
+    pub fn is_candidate(&self, entity: Entity) -> bool {
+        self.request.candidates.contains(&entity)
+    }
+
+    pub fn can_confirm(&self) -> bool {
+        self.selected.len() >= self.request.min && self.selected.len() <= self.request.max
+    }
+}
 
-impl TargetAgentAction {}
+/// Marker of the highlight spawned on the candidates of the current selection
+#[derive(Component)]
+pub struct TargetHighlight;
 
-impl AgentAction for TargetAgentAction {}
+/// Triggered on the client when the server asked it to choose targets
+#[derive(Event, Clone, Debug)]
+pub struct ClientTargetRequested {
+    pub board: Entity,
+    pub group: String,
+    pub min: usize,
+    pub max: usize,
+}
 
 pub(crate) fn target_agent_action_callback(
     input: In<AgentActionInput<TargetAgentAction>>,
     mut commands: Commands,
-    boards: Query<&Board>,
-    slots_agents: Query<&AgentOwned, With<BoardSlot>>,
+    highlights: Query<Entity, With<TargetHighlight>>,
     mut meshes: ResMut<Assets<Mesh>>,
 ) {
-    println!("Target agent action callback");
-    let board = match boards.get(input.board) {
-        Ok(board) => board,
-        Err(_) => {
-            error!("No matching board entity found in the summon action input");
-            return;
-        }
-    };
-    for slot in board.cache.get_slots() {
-        if true
-        /*slot_agent.0 == client_agent*/ /* Modify when client know which agent he play */
-        {
-            commands
-                .entity(*slot.1)
-                .insert(On::<Pointer<Click>>::run(
-                    move |event: Listener<Pointer<Click>>, mut commands: Commands| {
-                        commands.entity(event.listener()).despawn_descendants();
-                    },
-                ))
-                .with_children(|parent| {
-                    parent.spawn((PbrBundle {
+    let input = input.0;
+
+    // A new request replace the previous one, the server already moved on
+    for highlight in highlights.iter() {
+        commands.entity(highlight).despawn_recursive();
+    }
+
+    for candidate in input.data.candidates.iter() {
+        let Some(mut entity) = commands.get_entity(*candidate) else {
+            warn!(
+                "Target candidate {:?} does not exist on the client, it can't be selected",
+                candidate
+            );
+            continue;
+        };
+
+        entity
+            .insert(On::<Pointer<Click>>::run(
+                |event: Listener<Pointer<Click>>, mut commands: Commands| {
+                    commands.add(ToggleTargetCommand(event.listener()));
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    PbrBundle {
                         mesh: meshes.add(Mesh::from(Cuboid::new(0.1, 0.1, 0.1))),
                         ..default()
-                    },));
-                });
+                    },
+                    TargetHighlight,
+                ));
+            });
+    }
+
+    commands.trigger(ClientTargetRequested {
+        board: input.board,
+        group: input.data.group.clone(),
+        min: input.data.min,
+        max: input.data.max,
+    });
+    commands.insert_resource(TargetSelection {
+        board: input.board,
+        request: input.data,
+        selected: Vec::new(),
+    });
+}
+
+/// Select or unselect a candidate of the current selection, the selection is sent once the max is reached
+pub struct ToggleTargetCommand(pub Entity);
+
+impl Command for ToggleTargetCommand {
+    fn apply(self, world: &mut World) {
+        let Some(mut selection) = world.get_resource_mut::<TargetSelection>() else {
+            return;
+        };
+        if !selection.is_candidate(self.0) {
+            return;
+        }
+
+        if let Some(index) = selection.selected.iter().position(|e| *e == self.0) {
+            selection.selected.remove(index);
+        } else if selection.selected.len() < selection.request.max {
+            selection.selected.push(self.0);
+        }
+
+        if selection.selected.len() == selection.request.max {
+            ConfirmTargetsCommand.apply(world);
         }
     }
 }
+
+/// Send the current selection to the server, used by the UI when less than the max can be chosen
+pub struct ConfirmTargetsCommand;
+
+impl Command for ConfirmTargetsCommand {
+    fn apply(self, world: &mut World) {
+        let Some(selection) = world.get_resource::<TargetSelection>() else {
+            return;
+        };
+        if !selection.can_confirm() {
+            warn!(
+                "Can't confirm {} targets, between {} and {} are needed",
+                selection.selected.len(),
+                selection.request.min,
+                selection.request.max
+            );
+            return;
+        }
+
+        let selection = end_target_selection(world).unwrap();
+
+        world.send_event(TargetResponsePacket::new(
+            selection.board,
+            selection.request.request,
+            selection.selected,
+        ));
+    }
+}
+
+/// Give up the current selection, the server gives back the costs already paid for the activation
+/// Only the costs of an activation can be cancelled, the targets of a resolving effect have to be chosen
+pub struct CancelTargetsCommand;
+
+impl Command for CancelTargetsCommand {
+    fn apply(self, world: &mut World) {
+        let Some(selection) = end_target_selection(world) else {
+            return;
+        };
+
+        world.send_event(TargetResponsePacket::cancel(
+            selection.board,
+            selection.request.request,
+        ));
+    }
+}
+
+/// Remove the selection with its highlights and click handlers
+fn end_target_selection(world: &mut World) -> Option<TargetSelection> {
+    let selection = world.remove_resource::<TargetSelection>()?;
+
+    for candidate in selection.request.candidates.iter() {
+        if let Some(mut entity) = world.get_entity_mut(*candidate) {
+            entity.remove::<On<Pointer<Click>>>();
+        }
+    }
+    let highlights: Vec<Entity> = world
+        .query_filtered::<Entity, With<TargetHighlight>>()
+        .iter(world)
+        .collect();
+    for highlight in highlights {
+        world.entity_mut(highlight).despawn_recursive();
+    }
+
+    Some(selection)
+}
@@ -15,10 +15,27 @@ pub struct BoardCache {
     pub on_hand_lookup: HashMap<Entity, BTreeSet<Entity>>,
     pub(crate) on_board_lookup: HashSet<Entity>,
 
+    /// The key is the agent entity and the value is his graveyard
+    /// Vec is used as the graveyard order is the order the cards were sent to it
+    pub on_graveyard_lookup: HashMap<Entity, Vec<Entity>>,
+
+    /// The key is the agent entity and the value is his exiled cards, ordered like the graveyard
+    pub on_exile_lookup: HashMap<Entity, Vec<Entity>>,
+
     /// Every entity that belong to a agent
     pub(crate) agent_lookup: HashMap<Entity, HashSet<Entity>>,
 
     pub(crate) on_field_lookup: HashSet<Entity>,
+
+    /// The key is the agent entity and the value is his deck entity
+    pub(crate) deck_lookup: HashMap<Entity, Entity>,
+
+    /// When each entity on the field or on a slot got there, used to order the continuous effects
+    pub(crate) field_timestamps: HashMap<Entity, u64>,
+    pub(crate) next_field_timestamp: u64,
+
+    /// The field changed since the continuous effects got applied, see [`crate::continuous_effects_system`]
+    pub(crate) continuous_dirty: bool,
 }
 
 impl BoardCache {
@@ -44,6 +61,9 @@ impl BoardCache {
     pub(crate) fn clean_agent_associate_values(&mut self, agent: Entity) {
         self.agent_lookup.remove(&agent);
         self.on_hand_lookup.remove(&agent);
+        self.on_graveyard_lookup.remove(&agent);
+        self.on_exile_lookup.remove(&agent);
+        self.deck_lookup.remove(&agent);
     }
 
     pub fn get_entities(&self) -> &HashSet<Entity> {
@@ -0,0 +1,600 @@
+use std::fmt::{self, Display, Formatter};
+
+use bevy::{ecs::world::Command, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    check_cooldown, check_costs, record_activation, send_rejection, start_activation, AgentOwned,
+    Board, BoardGameState, BoardStage, CooldownError, CostError, EffectRegistry, Effects, OnBoard,
+    PendingTargets, RejectionReason, NORMAL_EFFECT_SPEED,
+};
+
+use super::{BoardActionRunner, BoardSequence, BoardState, ChainLink, Tree};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum ChainError {
+    BoardFinished,
+    NotPlaying,
+    /// Only the turn agent can start a chain
+    NotTurnAgent,
+    /// A chain is open and another agent hold the priority
+    NoPriority,
+    NoChain,
+    NotOwner,
+    NotOnBoard,
+    UnknownEffect {
+        effect_index: usize,
+    },
+    /// Normal speed effects can only start a chain in the main stage of their owner turn
+    NormalSpeedTiming,
+    /// The effect is slower than the link it try to respond to
+    TooSlow {
+        speed: i32,
+        required: i32,
+    },
+    Cooldown(CooldownError),
+    Cost(CostError),
+    /// The agent is still paying the costs of another activation
+    ActivationPending,
+    /// The effect only has continuous modifiers, there is nothing to put on the chain
+    NotActivatable,
+}
+
+impl Display for ChainError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainError::BoardFinished => write!(f, "the match is finished"),
+            ChainError::NotPlaying => write!(f, "the agent is not playing on this board"),
+            ChainError::NotTurnAgent => write!(f, "only the turn agent can start a chain"),
+            ChainError::NoPriority => write!(f, "the agent does not have the priority"),
+            ChainError::NoChain => write!(f, "there is no chain to respond to"),
+            ChainError::NotOwner => write!(f, "the card belong to another agent"),
+            ChainError::NotOnBoard => write!(f, "the card is not on the board"),
+            ChainError::UnknownEffect { effect_index } => {
+                write!(f, "the card has no effect at the index {}", effect_index)
+            }
+            ChainError::NormalSpeedTiming => write!(
+                f,
+                "normal speed effects can only be activated in the main stage of your turn"
+            ),
+            ChainError::TooSlow { speed, required } => write!(
+                f,
+                "the effect speed {} is too slow to respond, the chain need at least {}",
+                speed, required
+            ),
+            ChainError::Cooldown(error) => write!(f, "{}", error),
+            ChainError::Cost(error) => write!(f, "{}", error),
+            ChainError::ActivationPending => {
+                write!(f, "the agent is still paying the costs of an activation")
+            }
+            ChainError::NotActivatable => write!(f, "the effect can't be activated"),
+        }
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+/// The outcome of an agent passing its priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriorityPass {
+    /// Another agent can now respond
+    Next(Entity),
+    /// Everyone passed, the chain must resolve
+    Resolve,
+}
+
+/// Triggered on the server when an effect got added to the chain of a board, the index start at 0 for the first link
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ChainLinkAdded {
+    pub board: Entity,
+    pub link: ChainLink,
+    pub index: usize,
+}
+
+/// Triggered on the server when an agent get the priority to respond to the chain of a board
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PriorityGiven {
+    pub board: Entity,
+    pub agent: Entity,
+}
+
+/// Triggered on the server for every link of a closed chain, from the last added to the first
+/// The effect of the link is only queued in [`BoardState::tick_triggers`], it runs on the next effect tick
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ChainLinkQueued {
+    pub board: Entity,
+    pub link: ChainLink,
+    pub index: usize,
+}
+
+/// Triggered on the server once every agent passed and every link of the chain got queued to run
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ChainResolved {
+    pub board: Entity,
+}
+
+impl BoardState {
+    pub fn get_chain(&self) -> Option<&Tree> {
+        self.current_tree.as_ref()
+    }
+
+    /// The agent that can respond to the chain, None if no chain is open
+    pub fn get_priority(&self) -> Option<Entity> {
+        self.current_tree.as_ref().map(|tree| tree.get_priority())
+    }
+
+    /// Check if the agent is allowed to add a link of this speed to the chain right now
+    /// Responses need a speed greater or equal to the last link, normal speed effects can't respond
+    /// and can only start a chain in the main stage of their owner turn while nothing else is going on
+    pub fn can_activate_effect(&self, agent: Entity, speed: i32) -> Result<(), ChainError> {
+        if self.is_finished() {
+            return Err(ChainError::BoardFinished);
+        }
+        if !self.agents.contains(&agent) || self.is_defeated(agent) {
+            return Err(ChainError::NotPlaying);
+        }
+
+        match &self.current_tree {
+            Some(tree) => {
+                if tree.get_priority() != agent {
+                    return Err(ChainError::NoPriority);
+                }
+                if speed <= NORMAL_EFFECT_SPEED {
+                    return Err(ChainError::NormalSpeedTiming);
+                }
+
+                let required = tree.last_link().map_or(speed, |link| link.speed);
+                if speed < required {
+                    return Err(ChainError::TooSlow { speed, required });
+                }
+                Ok(())
+            }
+            None if speed <= NORMAL_EFFECT_SPEED => {
+                if self.current_turn_agent != Some(agent) {
+                    return Err(ChainError::NotTurnAgent);
+                }
+                if self.stage != BoardStage::Main
+                    || !matches!(self.game_state, BoardGameState::Open)
+                {
+                    return Err(ChainError::NormalSpeedTiming);
+                }
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Start a chain or respond to the current one, returns the agent getting the priority
+    pub fn activate_effect(&mut self, link: ChainLink) -> Result<Entity, ChainError> {
+        self.can_activate_effect(link.agent, link.speed)?;
+
+        let priority = self.next_playing_agent(link.agent);
+
+        if let Some(ref mut tree) = self.current_tree {
+            tree.push_link(link, priority);
+        } else {
+            self.current_tree = Some(Tree::new(link, priority));
+        }
+        Ok(priority)
+    }
+
+    pub fn pass_priority(&mut self, agent: Entity) -> Result<PriorityPass, ChainError> {
+        if self.is_finished() {
+            return Err(ChainError::BoardFinished);
+        }
+        if !self.agents.contains(&agent) || self.is_defeated(agent) {
+            return Err(ChainError::NotPlaying);
+        }
+
+        let next = self.next_playing_agent(agent);
+        let playing = self
+            .agents
+            .iter()
+            .filter(|agent| !self.is_defeated(**agent))
+            .count();
+
+        let Some(ref mut tree) = self.current_tree else {
+            return Err(ChainError::NoChain);
+        };
+        if tree.get_priority() != agent {
+            return Err(ChainError::NoPriority);
+        }
+
+        tree.pass(agent, next);
+
+        if playing
+            <= self
+                .agents
+                .iter()
+                .filter(|agent| tree.has_passed(**agent))
+                .count()
+        {
+            Ok(PriorityPass::Resolve)
+        } else {
+            Ok(PriorityPass::Next(next))
+        }
+    }
+
+    /// Close the chain and return its links in resolution order, the last added first
+    pub(crate) fn take_chain_links(&mut self) -> Vec<ChainLink> {
+        let mut links = Vec::new();
+
+        if let Some(mut tree) = self.current_tree.take() {
+            while let Some(link) = tree.pop_link() {
+                links.push(link);
+            }
+        }
+        links
+    }
+
+    /// The next agent in turn order that is still in the match
+    fn next_playing_agent(&self, agent: Entity) -> Entity {
+        let start = self
+            .agents
+            .iter()
+            .position(|a| *a == agent)
+            .unwrap_or(self.current_turn_agent_index);
+
+        (1..=self.agents.len())
+            .map(|offset| self.agents[(start + offset) % self.agents.len()])
+            .find(|agent| !self.is_defeated(*agent))
+            .unwrap_or(agent)
+    }
+}
+
+/// Check the card side of an activation, the board side is checked by [`BoardState::can_activate_effect`]
+pub fn validate_activation(
+    world: &World,
+    board_entity: Entity,
+    link: &ChainLink,
+) -> Result<(), ChainError> {
+    let Some(board) = world.get::<Board>(board_entity) else {
+        return Err(ChainError::NotPlaying);
+    };
+
+    if has_pending_activation(world, board_entity, link.agent) {
+        return Err(ChainError::ActivationPending);
+    }
+    if world.get::<OnBoard>(link.card).map(|on_board| on_board.0) != Some(board_entity) {
+        return Err(ChainError::NotOnBoard);
+    }
+    if world.get::<AgentOwned>(link.card).map(|owned| owned.0) != Some(link.agent) {
+        return Err(ChainError::NotOwner);
+    }
+
+    let Some(effect) = world
+        .get::<Effects>(link.card)
+        .and_then(|effects| effects.get_effect(link.effect_index))
+    else {
+        return Err(ChainError::UnknownEffect {
+            effect_index: link.effect_index,
+        });
+    };
+
+    let registered = world
+        .get_resource::<EffectRegistry>()
+        .map_or(false, |registry| {
+            registry.get_effect(&effect.get_effect_id()).is_some()
+        });
+    if !registered && effect.get_actions().is_empty() && !effect.get_continuous().is_empty() {
+        return Err(ChainError::NotActivatable);
+    }
+
+    // The speed always come from the effect itself, never from the requester
+    board
+        .state
+        .can_activate_effect(link.agent, effect.get_effect_speed())?;
+
+    check_cooldown(world, board, link).map_err(ChainError::Cooldown)?;
+    check_costs(world, board_entity, link).map_err(ChainError::Cost)
+}
+
+/// Activate an effect of a card, starting a chain or responding to the current one, server side only
+pub struct ActivateEffectCommand {
+    pub board: Entity,
+    pub link: ChainLink,
+}
+
+impl ActivateEffectCommand {
+    pub fn new(board: Entity, agent: Entity, card: Entity, effect_index: usize) -> Self {
+        Self {
+            board,
+            link: ChainLink {
+                card,
+                effect_index,
+                agent,
+                speed: NORMAL_EFFECT_SPEED,
+            },
+        }
+    }
+}
+
+impl Command for ActivateEffectCommand {
+    fn apply(mut self, world: &mut World) {
+        if let Some(effect) = world
+            .get::<Effects>(self.link.card)
+            .and_then(|effects| effects.get_effect(self.link.effect_index))
+        {
+            self.link.speed = effect.get_effect_speed();
+        }
+
+        if let Err(e) = validate_activation(world, self.board, &self.link) {
+            warn!(
+                "ActivateEffectCommand: agent {:?} could not activate the effect {} of {:?}: {}",
+                self.link.agent, self.link.effect_index, self.link.card, e
+            );
+            send_rejection(
+                world,
+                self.board,
+                self.link.agent,
+                RejectionReason::Chain(e),
+            );
+            return;
+        }
+
+        // The costs are paid first, the effect goes on the chain once they are all paid
+        start_activation(world, self.board, self.link);
+    }
+}
+
+/// Put the link on the chain and give the priority, the link must have been validated with [`validate_activation`]
+/// The cooldown is checked again as it may have changed while the costs were paid
+pub(crate) fn push_activation(
+    world: &mut World,
+    board_entity: Entity,
+    link: ChainLink,
+) -> Result<(), ChainError> {
+    let board = world
+        .get::<Board>(board_entity)
+        .ok_or(ChainError::NotPlaying)?;
+    check_cooldown(world, board, &link).map_err(ChainError::Cooldown)?;
+
+    let mut board = world
+        .get_mut::<Board>(board_entity)
+        .ok_or(ChainError::NotPlaying)?;
+    let priority = board.state.activate_effect(link)?;
+    let index = board.state.get_chain().map_or(0, |tree| tree.len() - 1);
+
+    record_activation(world, board_entity, &link);
+
+    world.trigger(ChainLinkAdded {
+        board: board_entity,
+        link,
+        index,
+    });
+    world.trigger(PriorityGiven {
+        board: board_entity,
+        agent: priority,
+    });
+    Ok(())
+}
+
+/// Pass the priority of the agent, resolve the chain if everyone passed, server side only
+pub struct PassPriorityCommand {
+    pub board: Entity,
+    pub agent: Entity,
+}
+
+impl PassPriorityCommand {
+    pub fn new(board: Entity, agent: Entity) -> Self {
+        Self { board, agent }
+    }
+}
+
+impl Command for PassPriorityCommand {
+    fn apply(self, world: &mut World) {
+        if has_pending_activation(world, self.board, self.agent) {
+            warn!(
+                "PassPriorityCommand: agent {:?} can't pass on the board {:?} while paying the costs of an activation",
+                self.agent, self.board
+            );
+            send_rejection(
+                world,
+                self.board,
+                self.agent,
+                RejectionReason::Chain(ChainError::ActivationPending),
+            );
+            return;
+        }
+
+        let Some(mut board) = world.get_mut::<Board>(self.board) else {
+            warn!(
+                "PassPriorityCommand: board {:?} does not exist, skipping",
+                self.board
+            );
+            return;
+        };
+
+        match board.state.pass_priority(self.agent) {
+            Ok(PriorityPass::Next(agent)) => {
+                world.trigger(PriorityGiven {
+                    board: self.board,
+                    agent,
+                });
+            }
+            Ok(PriorityPass::Resolve) => {
+                let links = board.state.take_chain_links();
+                let len = links.len();
+
+                for link in links.iter() {
+                    board.state.trigger_effect(link.card, link.effect_index);
+                }
+
+                for (i, link) in links.into_iter().enumerate() {
+                    world.trigger(ChainLinkQueued {
+                        board: self.board,
+                        link,
+                        index: len - 1 - i,
+                    });
+                }
+                world.trigger(ChainResolved { board: self.board });
+            }
+            Err(e) => {
+                warn!(
+                    "PassPriorityCommand: agent {:?} could not pass on the board {:?}: {}",
+                    self.agent, self.board, e
+                );
+                send_rejection(world, self.board, self.agent, RejectionReason::Chain(e));
+            }
+        }
+    }
+}
+
+fn has_pending_activation(world: &World, board: Entity, agent: Entity) -> bool {
+    world
+        .get_resource::<PendingTargets>()
+        .map_or(false, |pending_targets| {
+            pending_targets.has_pending_activation(board, agent)
+        })
+}
+
+/// Pass the priority of an agent that did not answer in time
+pub struct AutoPassRunner {
+    pub board: Entity,
+    pub agent: Entity,
+}
+
+impl BoardActionRunner for AutoPassRunner {
+    fn execute(&self, commands: &mut Commands) {
+        info!(
+            "Agent {:?} did not answer the chain of the board {:?} in time, passing",
+            self.agent, self.board
+        );
+        commands.add(PassPriorityCommand::new(self.board, self.agent));
+    }
+}
+
+/// Start the answer timeout of the agent getting the priority, it replaces the timeout of the previous agent
+pub(crate) fn priority_timeout_observer(
+    trigger: Trigger<PriorityGiven>,
+    mut boards: Query<&mut Board>,
+) {
+    let event = trigger.event();
+
+    if let Ok(mut board) = boards.get_mut(event.board) {
+        if board.state.is_finished() {
+            return;
+        }
+
+        let timeout = board.state.rules.priority_timeout;
+        if let Some(tree) = board.state.current_tree.as_mut() {
+            tree.priority_timeout = Some(BoardSequence::new(
+                timeout,
+                AutoPassRunner {
+                    board: event.board,
+                    agent: event.agent,
+                },
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoardRng, MatchResult};
+
+    fn link(agent: Entity, speed: i32) -> ChainLink {
+        ChainLink {
+            card: Entity::from_raw(10),
+            effect_index: 0,
+            agent,
+            speed,
+        }
+    }
+
+    fn main_stage_state(agents: Vec<Entity>) -> BoardState {
+        let mut state = BoardState::new(agents, BoardRng::from_seed(0));
+        state.game_start();
+        state.stage = BoardStage::Main;
+        state
+    }
+
+    #[test]
+    fn normal_speed_only_start_a_chain_in_the_turn_agent_main_stage() {
+        let (a, b) = (Entity::from_raw(1), Entity::from_raw(2));
+        let mut state = main_stage_state(vec![a, b]);
+
+        assert_eq!(state.can_activate_effect(a, NORMAL_EFFECT_SPEED), Ok(()));
+        assert_eq!(
+            state.can_activate_effect(b, NORMAL_EFFECT_SPEED),
+            Err(ChainError::NotTurnAgent)
+        );
+        assert_eq!(state.can_activate_effect(b, 2), Ok(()));
+
+        state.stage = BoardStage::Battle;
+        assert_eq!(
+            state.can_activate_effect(a, NORMAL_EFFECT_SPEED),
+            Err(ChainError::NormalSpeedTiming)
+        );
+    }
+
+    #[test]
+    fn responses_need_the_priority_and_enough_speed() {
+        let (a, b) = (Entity::from_raw(1), Entity::from_raw(2));
+        let mut state = main_stage_state(vec![a, b]);
+
+        assert_eq!(state.activate_effect(link(a, 2)), Ok(b));
+        assert_eq!(state.can_activate_effect(a, 3), Err(ChainError::NoPriority));
+        assert_eq!(
+            state.can_activate_effect(b, NORMAL_EFFECT_SPEED),
+            Err(ChainError::NormalSpeedTiming)
+        );
+        assert_eq!(state.activate_effect(link(b, 2)), Ok(a));
+        assert_eq!(
+            state.can_activate_effect(a, NORMAL_EFFECT_SPEED),
+            Err(ChainError::NormalSpeedTiming)
+        );
+    }
+
+    #[test]
+    fn slower_responses_are_rejected() {
+        let (a, b) = (Entity::from_raw(1), Entity::from_raw(2));
+        let mut state = main_stage_state(vec![a, b]);
+
+        state.activate_effect(link(a, 3)).unwrap();
+        assert_eq!(
+            state.can_activate_effect(b, 2),
+            Err(ChainError::TooSlow {
+                speed: 2,
+                required: 3
+            })
+        );
+    }
+
+    #[test]
+    fn chain_resolve_once_every_agent_passed() {
+        let (a, b) = (Entity::from_raw(1), Entity::from_raw(2));
+        let mut state = main_stage_state(vec![a, b]);
+
+        assert_eq!(state.pass_priority(a), Err(ChainError::NoChain));
+
+        state.activate_effect(link(a, NORMAL_EFFECT_SPEED)).unwrap();
+        assert_eq!(state.pass_priority(a), Err(ChainError::NoPriority));
+        assert_eq!(state.pass_priority(b), Ok(PriorityPass::Next(a)));
+        assert_eq!(state.pass_priority(a), Ok(PriorityPass::Resolve));
+
+        let links = state.take_chain_links();
+        assert_eq!(links.len(), 1);
+        assert!(state.get_chain().is_none());
+    }
+
+    #[test]
+    fn finished_boards_and_defeated_agents_cant_pass() {
+        let (a, b) = (Entity::from_raw(1), Entity::from_raw(2));
+        let mut state = main_stage_state(vec![a, b]);
+
+        state.activate_effect(link(a, NORMAL_EFFECT_SPEED)).unwrap();
+        state.defeated.push(b);
+        assert_eq!(state.pass_priority(b), Err(ChainError::NotPlaying));
+
+        state.game_state = BoardGameState::Finished {
+            result: MatchResult::Win { winner: a },
+        };
+        assert_eq!(state.pass_priority(a), Err(ChainError::BoardFinished));
+        assert_eq!(
+            state.can_activate_effect(a, 2),
+            Err(ChainError::BoardFinished)
+        );
+    }
+}
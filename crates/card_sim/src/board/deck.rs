@@ -1,8 +1,23 @@
-use bevy::prelude::*;
-use rand::seq::SliceRandom;
+use bevy::{
+    ecs::{
+        component::{ComponentHooks, StorageType},
+        world::Command,
+    },
+    prelude::*,
+};
+use epithet::units::UnitRegistry;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
 
-use crate::CardId;
+use crate::{
+    agent_client_id, AgentOwned, Board, Card, CardAttribute, CardBundle, CardId, CardRegistry,
+    CardVisibility, DefeatReason, Effects, OnBoard, OnHand,
+};
 
+use super::BoardCache;
+
+/// The content of a deck, server side only as the order of the cards is hidden information
+/// Clients only know the number of cards left through [`BoardDeck`]
 #[derive(Component)]
 pub struct Deck {
     // Using a vec for fasted iteration
@@ -10,8 +25,6 @@ pub struct Deck {
     cards: Vec<CardId>,
 }
 
-use rand::thread_rng;
-
 impl Deck {
     pub fn new(cards: Vec<CardId>) -> Self {
         Self { cards }
@@ -21,7 +34,262 @@ impl Deck {
         self.cards.pop()
     }
 
-    pub fn shuffle(&mut self) {
-        self.cards.shuffle(&mut thread_rng());
+    /// Shuffle the deck with the given rng, use the board rng ([`crate::BoardRng`]) so the shuffle can be replayed
+    pub fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.cards.shuffle(rng);
+    }
+
+    /// Remove up to count cards with this id, starting from the top of the deck, returns how many got removed
+    pub fn take(&mut self, card: CardId, count: usize) -> usize {
+        let mut taken = 0;
+
+        for i in (0..self.cards.len()).rev() {
+            if taken == count {
+                break;
+            }
+            if self.cards[i] == card {
+                self.cards.remove(i);
+                taken += 1;
+            }
+        }
+        taken
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+}
+
+/// Mark the entity as the deck of its owning agent, the value is the number of cards left in the deck
+/// Replicated to every clients and kept in sync with the [`Deck`] of the server
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+pub struct BoardDeck(pub usize);
+
+impl Component for BoardDeck {
+    const STORAGE_TYPE: StorageType = StorageType::Table;
+
+    fn register_component_hooks(hooks: &mut ComponentHooks) {
+        hooks.on_insert(|mut world, entity, _component_id| {
+            let board_entity = world.get::<OnBoard>(entity).cloned();
+            let agent = world.get::<AgentOwned>(entity).cloned();
+
+            if let (Some(board_entity), Some(agent)) = (board_entity, agent) {
+                if let Some(mut board) = world.get_mut::<Board>(board_entity.0) {
+                    board.cache.insert_deck(agent.0, entity);
+                }
+            }
+        });
+        hooks.on_remove(|mut world, entity, _component_id| {
+            let board_entity = world.get::<OnBoard>(entity).cloned();
+            let agent = world.get::<AgentOwned>(entity).cloned();
+
+            if let (Some(board_entity), Some(agent)) = (board_entity, agent) {
+                if let Some(mut board) = world.get_mut::<Board>(board_entity.0) {
+                    board.cache.remove_deck(agent.0, &entity);
+                }
+            }
+        });
+    }
+}
+
+impl BoardCache {
+    pub(crate) fn insert_deck(&mut self, agent: Entity, entity: Entity) {
+        if let Some(old_deck) = self.deck_lookup.insert(agent, entity) {
+            if old_deck != entity {
+                error!("Agent {:?} got a second deck {:?} while already having the deck {:?}, only the new one will be used", agent, entity, old_deck);
+            }
+        }
+    }
+
+    pub(crate) fn remove_deck(&mut self, agent: Entity, entity: &Entity) -> bool {
+        if self.deck_lookup.get(&agent) == Some(entity) {
+            self.deck_lookup.remove(&agent);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn get_deck(&self, agent: &Entity) -> Option<&Entity> {
+        self.deck_lookup.get(agent)
+    }
+}
+
+pub(crate) fn deck_count_system(mut decks: Query<(&Deck, &mut BoardDeck), Changed<Deck>>) {
+    for (deck, mut board_deck) in decks.iter_mut() {
+        if board_deck.0 != deck.len() {
+            board_deck.0 = deck.len();
+        }
+    }
+}
+
+/// Triggered on the server for every card drawn by a [`DrawCommand`]
+#[derive(Event, Clone, Debug)]
+pub struct CardDrawn {
+    pub board: Entity,
+    pub agent: Entity,
+    pub card: Entity,
+}
+
+/// Draw cards from the agent deck to his hand, server side only
+/// The drawn cards are only visible to the client controlling the agent
+pub struct DrawCommand {
+    pub board: Entity,
+    pub agent: Entity,
+    pub count: usize,
+}
+
+impl DrawCommand {
+    pub fn new(board: Entity, agent: Entity, count: usize) -> Self {
+        Self {
+            board,
+            agent,
+            count,
+        }
+    }
+}
+
+impl Command for DrawCommand {
+    fn apply(self, world: &mut World) {
+        let deck_entity = match world
+            .get::<Board>(self.board)
+            .and_then(|board| board.cache.get_deck(&self.agent).copied())
+        {
+            Some(deck_entity) => deck_entity,
+            None => {
+                warn!(
+                    "DrawCommand: agent {:?} has no deck on the board {:?}, skipping",
+                    self.agent, self.board
+                );
+                return;
+            }
+        };
+
+        let mut drawn = Vec::with_capacity(self.count);
+
+        if let Some(mut deck) = world.get_mut::<Deck>(deck_entity) {
+            for _ in 0..self.count {
+                match deck.draw() {
+                    Some(card_id) => drawn.push(card_id),
+                    None => break,
+                }
+            }
+        } else {
+            error!("DrawCommand: the deck entity {:?} has no deck content, this should only happen on a client", deck_entity);
+            return;
+        }
+
+        // Having to draw from an empty deck lose the match
+        if drawn.len() < self.count {
+            if let Some(mut board) = world.get_mut::<Board>(self.board) {
+                board.state.defeat_agent(self.agent, DefeatReason::DeckOut);
+            }
+        }
+
+        for card_id in drawn {
+            let card = spawn_in_hand(world, self.board, self.agent, card_id);
+            world.trigger(CardDrawn {
+                board: self.board,
+                agent: self.agent,
+                card,
+            });
+        }
+    }
+}
+
+/// Spawn a card in the hand of the agent, only visible to the client controlling the agent
+pub(crate) fn spawn_in_hand(
+    world: &mut World,
+    board: Entity,
+    agent: Entity,
+    card_id: CardId,
+) -> Entity {
+    let client_id = agent_client_id(world, agent);
+    let data = world
+        .get_resource::<CardRegistry>()
+        .and_then(|registry| registry.get(&card_id))
+        .cloned();
+    let (card_bundle, effects) = match &data {
+        Some(data) => data.create_instance(),
+        None => (
+            CardBundle {
+                card_attribute: CardAttribute::new(card_id),
+                ..default()
+            },
+            Effects::default(),
+        ),
+    };
+    let unit = world
+        .get_resource::<UnitRegistry>()
+        .map(|registry| registry.get_unit::<Card>());
+    if unit.is_none() {
+        error!(
+            "spawn_in_hand: the UnitRegistry resource does not exist, the card {} is spawned without unit",
+            card_id.0
+        );
+    }
+
+    let mut card = world.spawn((
+        CardBundle {
+            card_visibility: CardVisibility::new(client_id.into_iter().collect(), false),
+            ..card_bundle
+        },
+        effects,
+        OnBoard(board),
+        OnHand,
+        AgentOwned(agent),
+    ));
+
+    if let Some(unit) = unit {
+        card.insert(unit);
+    }
+    if let Some(data) = data {
+        data.insert_effect_triggers(&mut card);
+    }
+    card.id()
+}
+
+/// Take cards with the given id from the agent deck and add them to his hand, server side only
+pub struct SearchDeckCommand {
+    pub board: Entity,
+    pub agent: Entity,
+    pub card: CardId,
+    pub count: usize,
+}
+
+impl Command for SearchDeckCommand {
+    fn apply(self, world: &mut World) {
+        let Some(deck_entity) = world
+            .get::<Board>(self.board)
+            .and_then(|board| board.cache.get_deck(&self.agent).copied())
+        else {
+            warn!(
+                "SearchDeckCommand: agent {:?} has no deck on the board {:?}, skipping",
+                self.agent, self.board
+            );
+            return;
+        };
+
+        let Some(mut deck) = world.get_mut::<Deck>(deck_entity) else {
+            error!("SearchDeckCommand: the deck entity {:?} has no deck content, this should only happen on a client", deck_entity);
+            return;
+        };
+
+        let found = deck.take(self.card, self.count);
+
+        if found < self.count {
+            info!(
+                "SearchDeckCommand: only {} of the {} searched cards {} were in the deck",
+                found, self.count, self.card.0
+            );
+        }
+
+        for _ in 0..found {
+            spawn_in_hand(world, self.board, self.agent, self.card);
+        }
     }
 }
@@ -0,0 +1,120 @@
+use bevy::{
+    ecs::{
+        component::{ComponentHooks, StorageType},
+        world::Command,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agent_client_id, AgentOwned, Board, CardVisibility, OnBoard, OnField, OnGraveyard, OnHand,
+    OnSlot, ReflectTag,
+};
+
+use super::BoardCache;
+
+/// Mark a card as removed from the game (banished) by its owning agent
+/// Face down exiled cards only reveal their [`crate::CardAttribute`] to the owner
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Tag)]
+pub enum OnExile {
+    FaceUp,
+    FaceDown,
+}
+
+impl Component for OnExile {
+    const STORAGE_TYPE: StorageType = StorageType::Table;
+
+    fn register_component_hooks(hooks: &mut ComponentHooks) {
+        hooks.on_insert(|mut world, entity, _component_id| {
+            let board_entity = world.get::<OnBoard>(entity).cloned();
+            let player = world.get::<AgentOwned>(entity).cloned();
+
+            if let Some(board_entity) = board_entity {
+                if let Some(mut board) = world.get_mut::<Board>(board_entity.0) {
+                    if let Some(player) = player {
+                        board.cache.insert_on_exile(player.0, entity);
+                    }
+                }
+            }
+        });
+        hooks.on_remove(|mut world, entity, _component_id| {
+            let board_entity = world.get::<OnBoard>(entity).cloned();
+            let player = world.get::<AgentOwned>(entity).cloned();
+
+            if let Some(board_entity) = board_entity {
+                if let Some(mut board) = world.get_mut::<Board>(board_entity.0) {
+                    if let Some(player) = player {
+                        board.cache.remove_from_exile(player.0, &entity);
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl BoardCache {
+    pub(crate) fn insert_on_exile(&mut self, agent: Entity, entity: Entity) {
+        let exile = self.on_exile_lookup.entry(agent).or_default();
+
+        if !exile.contains(&entity) {
+            exile.push(entity);
+        }
+    }
+
+    pub(crate) fn remove_from_exile(&mut self, agent: Entity, entity: &Entity) -> bool {
+        self.on_exile_lookup
+            .get_mut(&agent)
+            .map_or(false, |entities| {
+                let len = entities.len();
+
+                entities.retain(|e| e != entity);
+                len != entities.len()
+            })
+    }
+
+    /// The exiled cards of the agent, ordered from the first to the last card exiled, face up and face down cards are mixed
+    pub fn get_by_exile(&self, agent: &Entity) -> Option<&Vec<Entity>> {
+        self.on_exile_lookup.get(agent)
+    }
+}
+
+/// Move a card from wherever it is on the board to the exile of its owner
+/// Face up cards are revealed to every clients, face down cards are hidden from everyone but the owner
+pub struct ExileCommand {
+    pub entity: Entity,
+    pub exile: OnExile,
+}
+
+impl ExileCommand {
+    pub fn new(entity: Entity, exile: OnExile) -> Self {
+        Self { entity, exile }
+    }
+}
+
+impl Command for ExileCommand {
+    fn apply(self, world: &mut World) {
+        let Some(owner) = world.get::<AgentOwned>(self.entity).cloned() else {
+            warn!(
+                "ExileCommand: entity {:?} does not exist or has no owner, skipping",
+                self.entity
+            );
+            return;
+        };
+        let owner_client = agent_client_id(world, owner.0);
+        let mut entity = world.entity_mut(self.entity);
+
+        entity.remove::<(OnHand, OnSlot, OnField, OnGraveyard)>();
+
+        if let Some(mut visibility) = entity.get_mut::<CardVisibility>() {
+            match self.exile {
+                OnExile::FaceUp => visibility.visible_to_all = true,
+                OnExile::FaceDown => {
+                    *visibility = CardVisibility::new(owner_client.into_iter().collect(), false)
+                }
+            }
+        }
+        entity.insert(self.exile);
+    }
+}
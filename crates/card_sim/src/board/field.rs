@@ -5,11 +5,12 @@ use bevy::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{Board, OnBoard};
+use crate::{Board, OnBoard, ReflectTag};
 
 use super::BoardCache;
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy)]
+#[reflect(Tag)]
 pub struct OnField;
 
 impl Component for OnField {
@@ -36,10 +37,43 @@ impl Component for OnField {
 impl BoardCache {
     pub(crate) fn insert_on_field(&mut self, entity: Entity) {
         self.on_field_lookup.insert(entity);
+        self.stamp_field(entity);
     }
 
     pub(crate) fn remove_from_field(&mut self, entity: &Entity) -> bool {
-        self.on_field_lookup.remove(entity)
+        let removed = self.on_field_lookup.remove(entity);
+        self.unstamp_field(*entity);
+        removed
+    }
+
+    /// An entity already on the field or on a slot keep its timestamp when moving between them
+    pub(crate) fn stamp_field(&mut self, entity: Entity) {
+        if !self.field_timestamps.contains_key(&entity) {
+            self.field_timestamps
+                .insert(entity, self.next_field_timestamp);
+            self.next_field_timestamp += 1;
+        }
+        self.continuous_dirty = true;
+    }
+
+    /// Give back the timestamp the entity had before leaving the field, ex: a cost paid then given back
+    pub(crate) fn restore_field_timestamp(&mut self, entity: Entity, timestamp: u64) {
+        self.field_timestamps.insert(entity, timestamp);
+        self.continuous_dirty = true;
+    }
+
+    pub(crate) fn unstamp_field(&mut self, entity: Entity) {
+        let on_slot = self.on_slot_lookup.values().any(|e| *e == entity);
+
+        if !on_slot && !self.on_field_lookup.contains(&entity) {
+            self.field_timestamps.remove(&entity);
+        }
+        self.continuous_dirty = true;
+    }
+
+    /// When the entity entered the field or a slot, a lower timestamp entered first
+    pub fn get_field_timestamp(&self, entity: Entity) -> Option<u64> {
+        self.field_timestamps.get(&entity).copied()
     }
 
     pub fn is_on_field(&self, entity: Entity) -> bool {
@@ -0,0 +1,129 @@
+use bevy::{
+    ecs::{
+        component::{ComponentHooks, StorageType},
+        world::Command,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AgentOwned, Board, CardVisibility, OnBoard, OnExile, OnField, OnHand, OnSlot, ReflectTag,
+};
+
+use super::BoardCache;
+
+/// Mark a card as being in the graveyard (discard pile) of its owning agent
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy)]
+#[reflect(Tag)]
+pub struct OnGraveyard;
+
+impl Component for OnGraveyard {
+    const STORAGE_TYPE: StorageType = StorageType::Table;
+
+    fn register_component_hooks(hooks: &mut ComponentHooks) {
+        hooks.on_insert(|mut world, entity, _component_id| {
+            let board_entity = world.get::<OnBoard>(entity).cloned();
+            let player = world.get::<AgentOwned>(entity).cloned();
+
+            if let Some(board_entity) = board_entity {
+                if let Some(mut board) = world.get_mut::<Board>(board_entity.0) {
+                    if let Some(player) = player {
+                        board.cache.insert_on_graveyard(player.0, entity);
+                    }
+                }
+            }
+        });
+        hooks.on_remove(|mut world, entity, _component_id| {
+            let board_entity = world.get::<OnBoard>(entity).cloned();
+            let player = world.get::<AgentOwned>(entity).cloned();
+
+            if let Some(board_entity) = board_entity {
+                if let Some(mut board) = world.get_mut::<Board>(board_entity.0) {
+                    if let Some(player) = player {
+                        board.cache.remove_from_graveyard(player.0, &entity);
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl BoardCache {
+    pub(crate) fn insert_on_graveyard(&mut self, agent: Entity, entity: Entity) {
+        let graveyard = self.on_graveyard_lookup.entry(agent).or_default();
+
+        if !graveyard.contains(&entity) {
+            graveyard.push(entity);
+        }
+    }
+
+    pub(crate) fn remove_from_graveyard(&mut self, agent: Entity, entity: &Entity) -> bool {
+        self.on_graveyard_lookup
+            .get_mut(&agent)
+            .map_or(false, |entities| {
+                let len = entities.len();
+
+                entities.retain(|e| e != entity);
+                len != entities.len()
+            })
+    }
+
+    /// The graveyard of the agent, ordered from the first to the last card sent to it
+    pub fn get_by_graveyard(&self, agent: &Entity) -> Option<&Vec<Entity>> {
+        self.on_graveyard_lookup.get(agent)
+    }
+}
+
+/// Triggered on the server when a card on the field or on a slot got sent to the graveyard
+#[derive(Event, Clone, Debug)]
+pub struct CardDestroyed {
+    pub board: Entity,
+    pub card: Entity,
+}
+
+/// Move a card from wherever it is on the board to the graveyard of its owner, the card is revealed to every clients
+/// Entities without owner can't go to a graveyard and are despawned instead
+pub struct SendToGraveyardCommand(pub Entity);
+
+impl Command for SendToGraveyardCommand {
+    fn apply(self, world: &mut World) {
+        let Some(mut entity) = world.get_entity_mut(self.0) else {
+            warn!(
+                "SendToGraveyardCommand: entity {:?} does not exist, skipping",
+                self.0
+            );
+            return;
+        };
+
+        // Leaving the hand or the deck is a discard, not a destruction
+        let destroyed = if entity.contains::<OnField>() || entity.contains::<OnSlot>() {
+            entity.get::<OnBoard>().map(|on_board| on_board.0)
+        } else {
+            None
+        };
+
+        if !entity.contains::<AgentOwned>() {
+            entity.despawn_recursive();
+        } else {
+            put_in_graveyard(&mut entity);
+        }
+
+        if let Some(board) = destroyed {
+            world.trigger(CardDestroyed {
+                board,
+                card: self.0,
+            });
+        }
+    }
+}
+
+/// Move an owned card to the graveyard without triggering anything, used for costs
+pub(crate) fn put_in_graveyard(entity: &mut EntityWorldMut) {
+    entity.remove::<(OnHand, OnSlot, OnField, OnExile)>();
+
+    if let Some(mut visibility) = entity.get_mut::<CardVisibility>() {
+        visibility.visible_to_all = true;
+    }
+    entity.insert(OnGraveyard);
+}
@@ -1,16 +1,23 @@
 use std::collections::BTreeSet;
 
 use bevy::{
-    ecs::component::{ComponentHooks, StorageType},
+    ecs::{
+        component::{ComponentHooks, StorageType},
+        world::Command,
+    },
     prelude::*,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{AgentOwned, Board, OnBoard};
+use crate::{
+    agent_client_id, AgentOwned, Board, CardVisibility, OnBoard, OnExile, OnField, OnGraveyard,
+    OnSlot, ReflectTag,
+};
 
 use super::BoardCache;
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy)]
+#[reflect(Tag)]
 pub struct OnHand;
 
 impl Component for OnHand {
@@ -59,3 +66,33 @@ impl BoardCache {
         self.on_hand_lookup.get(agent)
     }
 }
+
+/// Move a card from wherever it is on the board back to the hand of its owner, the card get hidden from the other clients
+/// Entities without owner can't go to a hand and are despawned instead
+pub struct ReturnToHandCommand(pub Entity);
+
+impl Command for ReturnToHandCommand {
+    fn apply(self, world: &mut World) {
+        let Some(owner) = world.get::<AgentOwned>(self.0).map(|owned| owned.0) else {
+            if let Some(entity) = world.get_entity_mut(self.0) {
+                entity.despawn_recursive();
+            } else {
+                warn!(
+                    "ReturnToHandCommand: entity {:?} does not exist, skipping",
+                    self.0
+                );
+            }
+            return;
+        };
+        let client_id = agent_client_id(world, owner);
+
+        let mut entity = world.entity_mut(self.0);
+
+        entity.remove::<(OnSlot, OnField, OnGraveyard, OnExile)>();
+
+        if let Some(mut visibility) = entity.get_mut::<CardVisibility>() {
+            *visibility = CardVisibility::new(client_id.into_iter().collect(), false);
+        }
+        entity.insert(OnHand);
+    }
+}
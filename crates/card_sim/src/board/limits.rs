@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{BoardStage, RejectionReason};
+
+use super::BoardState;
+
+/// The agent actions counted during a turn
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TurnAction {
+    Summon,
+    /// Draws requested by the agent, draws coming from effects are not limited
+    Draw,
+    /// Stage changes going over at least one stage
+    StageSkip,
+}
+
+/// How many times an agent can do each action during his turn, None is unlimited
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone)]
+pub struct TurnLimits {
+    pub summons: Option<u32>,
+    pub draws: Option<u32>,
+    pub stage_skips: Option<u32>,
+}
+
+impl Default for TurnLimits {
+    fn default() -> Self {
+        Self {
+            summons: Some(1),
+            draws: None,
+            stage_skips: None,
+        }
+    }
+}
+
+impl TurnLimits {
+    pub fn get(&self, action: TurnAction) -> Option<u32> {
+        match action {
+            TurnAction::Summon => self.summons,
+            TurnAction::Draw => self.draws,
+            TurnAction::StageSkip => self.stage_skips,
+        }
+    }
+}
+
+/// The actions done by the turn agent since the start of the turn
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TurnCounters {
+    pub summons: u32,
+    pub draws: u32,
+    pub stage_skips: u32,
+}
+
+impl TurnCounters {
+    pub fn get(&self, action: TurnAction) -> u32 {
+        match action {
+            TurnAction::Summon => self.summons,
+            TurnAction::Draw => self.draws,
+            TurnAction::StageSkip => self.stage_skips,
+        }
+    }
+
+    fn get_mut(&mut self, action: TurnAction) -> &mut u32 {
+        match action {
+            TurnAction::Summon => &mut self.summons,
+            TurnAction::Draw => &mut self.draws,
+            TurnAction::StageSkip => &mut self.stage_skips,
+        }
+    }
+}
+
+impl BoardState {
+    pub fn get_turn_counters(&self) -> &TurnCounters {
+        &self.turn_counters
+    }
+
+    /// Check the turn limit of the action, the action is not recorded
+    pub fn can_perform(&self, action: TurnAction) -> Result<(), RejectionReason> {
+        match self.rules.turn_limits.get(action) {
+            Some(limit) if self.turn_counters.get(action) >= limit => {
+                Err(RejectionReason::TurnLimit { action, limit })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Count the action toward its turn limit, call it once the action got accepted
+    pub fn record_action(&mut self, action: TurnAction) {
+        *self.turn_counters.get_mut(action) += 1;
+    }
+
+    /// Does changing to the stage go over other stages
+    pub fn is_stage_skip(&self, stage: &BoardStage) -> bool {
+        let stages = &self.rules.stages;
+        let Some(target_index) = stages.iter().position(|s| s == stage) else {
+            return false;
+        };
+        let current_index = self.stage_index();
+
+        let distance = if target_index > current_index {
+            target_index - current_index
+        } else {
+            stages.len() - current_index + target_index
+        };
+        distance > 1
+    }
+}
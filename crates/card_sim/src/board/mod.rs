@@ -1,25 +1,43 @@
 mod agent_action;
 mod cache;
+mod chain;
+mod deck;
+mod exile;
 mod field;
+mod graveyard;
 mod hand;
+mod limits;
 mod packet;
 mod query;
+mod result;
+mod rng;
+mod rules;
 mod sequence;
 mod slot;
 mod stage;
 mod state;
+mod stats;
 mod tree;
 
 pub use agent_action::*;
 pub use cache::*;
+pub use chain::*;
+pub use deck::*;
+pub use exile::*;
 pub use field::*;
+pub use graveyard::*;
 pub use hand::*;
+pub use limits::*;
 pub use packet::*;
 pub use query::*;
+pub use result::*;
+pub use rng::*;
+pub use rules::*;
 pub use sequence::*;
 pub use slot::*;
 pub use stage::*;
 pub use state::*;
+pub use stats::*;
 pub use tree::*;
 
 use std::io::Cursor;
@@ -39,10 +57,13 @@ use bevy_replicon::{
     core::{
         ctx::WriteCtx,
         replication_registry::rule_fns::{DeserializeFn, RuleFns},
+        ClientId,
     },
     prelude::*,
+    server::ServerSet,
 };
-use epithet::agent::Agent;
+use epithet::agent::{Agent, AgentManager};
+use epithet::net::AuthManager;
 use epithet::units::UnitRegistry;
 use epithet::utils::LevelEntity;
 use serde::{Deserialize, Serialize};
@@ -50,8 +71,15 @@ use serde::{Deserialize, Serialize};
 pub(crate) fn board_plugin(app: &mut App) {
     app.add_plugins((board_packet_plugin, agent_action_plugin));
 
+    app.init_resource::<WinConditions>();
+    app.add_win_condition(LifeZeroCondition);
+
     app.register_type::<Board>();
     app.register_type::<OnSlot>();
+    app.register_type::<OnHand>();
+    app.register_type::<OnField>();
+    app.register_type::<OnGraveyard>();
+    app.register_type::<OnExile>();
 
     app.replicate_with::<Board>(
         RuleFns::default_mapped().with_in_place(Board::board_in_place_as_deserialize),
@@ -59,13 +87,25 @@ pub(crate) fn board_plugin(app: &mut App) {
     app.replicate_mapped::<OnBoard>();
     app.replicate::<OnHand>();
     app.replicate::<OnField>();
+    app.replicate::<OnGraveyard>();
+    app.replicate::<OnExile>();
     app.replicate_mapped::<AgentOwned>();
     app.replicate_mapped::<OnSlot>();
     app.replicate_mapped::<BoardSlot>();
+    app.replicate::<BoardDeck>();
+    app.replicate::<AgentStats>();
 
     app.add_systems(Update, board_state_update);
+    app.add_systems(Update, deck_count_system.before(ServerSet::Send));
+    app.add_systems(
+        Update,
+        match_result_system
+            .run_if(server_or_singleplayer)
+            .before(ServerSet::Send),
+    );
 
     app.observe(board_agent_removed_observer);
+    app.observe(priority_timeout_observer);
 }
 
 /// A component representing a board existing both as a marker and a lookup table to get entity on the board by common values
@@ -89,11 +129,25 @@ impl Board {
     pub fn new(agents: Vec<Entity>) -> Self {
         Self {
             client_is_on_board: None,
-            state: BoardState::new(agents),
+            state: BoardState::new(agents, BoardRng::from_entropy()),
+            cache: BoardCache::default(),
+        }
+    }
+
+    /// Create a board whose random outcomes are fully determined by the seed, used to replay a match
+    pub fn with_seed(agents: Vec<Entity>, seed: u64) -> Self {
+        Self {
+            client_is_on_board: None,
+            state: BoardState::new(agents, BoardRng::from_seed(seed)),
             cache: BoardCache::default(),
         }
     }
 
+    pub fn with_rules(mut self, rules: BoardRules) -> Self {
+        self.state.rules = rules;
+        self
+    }
+
     pub fn trigger_effect(&mut self, card: Entity, effect_index: usize) {
         self.state.trigger_effect(card, effect_index);
     }
@@ -105,6 +159,10 @@ impl Board {
         commands: &mut Commands,
         unit_registry: &UnitRegistry,
     ) {
+        commands
+            .entity(agent)
+            .insert(AgentStats::new(self.state.rules.starting_life));
+
         //TODO put it in the sim
         commands.spawn((
             SpatialBundle::default(),
@@ -117,6 +175,17 @@ impl Board {
             unit_registry.get_unit::<BoardSlot>(),
             Name::new("Slot"),
         ));
+        commands.spawn((
+            SpatialBundle::from_transform(Transform::from_xyz(-0.3, 0.0, 0.2)),
+            Deck::new(vec![]),
+            BoardDeck(0),
+            LevelEntity,
+            OnBoard(board_entity),
+            Replicated,
+            AgentOwned(agent),
+            unit_registry.get_unit::<BoardDeck>(),
+            Name::new("Deck"),
+        ));
     }
 
     pub fn board_in_place_as_deserialize(
@@ -132,6 +201,9 @@ impl Board {
         component.state.current_turn_agent_index =
             deserialized_board.state.current_turn_agent_index;
         component.state.stage = deserialized_board.state.stage;
+        component.state.turn = deserialized_board.state.turn;
+        component.state.turn_counters = deserialized_board.state.turn_counters;
+        component.state.rules = deserialized_board.state.rules;
 
         Ok(())
     }
@@ -150,6 +222,9 @@ impl Component for Board {
                         //TODO add as children all entities instead ?
                         OnBoard,
                         OnHand,
+                        OnGraveyard,
+                        OnExile,
+                        BoardDeck,
                         BoardSlot,
                         OnField,
                         OnSlot,
@@ -182,6 +257,9 @@ impl Component for OnBoard {
             let slot = world.get::<BoardSlot>(entity).cloned();
             let on_slot = world.get::<OnSlot>(entity).cloned();
             let on_hand = world.get::<OnHand>(entity).cloned();
+            let deck = world.get::<BoardDeck>(entity).cloned();
+            let on_graveyard = world.get::<OnGraveyard>(entity).cloned();
+            let on_exile = world.get::<OnExile>(entity).cloned();
 
             let temp_on_slot_slot = if let Some(on_slot) = &on_slot {
                 //TODO modify when we will be able to avoid clone with multiple get with system_state in deffered world
@@ -199,6 +277,15 @@ impl Component for OnBoard {
                         if on_hand.is_some() {
                             board.cache.insert_on_hand(agent.0, entity);
                         }
+                        if deck.is_some() {
+                            board.cache.insert_deck(agent.0, entity);
+                        }
+                        if on_graveyard.is_some() {
+                            board.cache.insert_on_graveyard(agent.0, entity);
+                        }
+                        if on_exile.is_some() {
+                            board.cache.insert_on_exile(agent.0, entity);
+                        }
                     }
                     if on_field.is_some() {
                         board.cache.insert_on_field(entity);
@@ -219,6 +306,9 @@ impl Component for OnBoard {
             let slot = world.get::<BoardSlot>(entity).cloned();
             let on_hand = world.get::<OnHand>(entity).cloned();
             let on_slot = world.get::<OnSlot>(entity).cloned();
+            let deck = world.get::<BoardDeck>(entity).cloned();
+            let on_graveyard = world.get::<OnGraveyard>(entity).cloned();
+            let on_exile = world.get::<OnExile>(entity).cloned();
 
             let temp_on_slot_slot = if let Some(on_slot) = &on_slot {
                 //TODO modify when we will be able to avoid clone with multiple get with system_state in deffered world
@@ -235,6 +325,15 @@ impl Component for OnBoard {
                         if on_hand.is_some() {
                             board.cache.remove_from_hand(agent.0, &entity);
                         }
+                        if deck.is_some() {
+                            board.cache.remove_deck(agent.0, &entity);
+                        }
+                        if on_graveyard.is_some() {
+                            board.cache.remove_from_graveyard(agent.0, &entity);
+                        }
+                        if on_exile.is_some() {
+                            board.cache.remove_from_exile(agent.0, &entity);
+                        }
                         board.cache.remove_from_agent(agent.0, &entity);
                     }
                     if let Some(slot) = slot {
@@ -269,6 +368,9 @@ impl Component for AgentOwned {
             let board_entity = world.get::<OnBoard>(entity).cloned();
             let agent = world.get::<AgentOwned>(entity).cloned();
             let on_hand = world.get::<OnHand>(entity).cloned();
+            let deck = world.get::<BoardDeck>(entity).cloned();
+            let on_graveyard = world.get::<OnGraveyard>(entity).cloned();
+            let on_exile = world.get::<OnExile>(entity).cloned();
 
             if let Some(board_entity) = board_entity {
                 if let Some(mut board) = world.get_mut::<Board>(board_entity.0) {
@@ -277,6 +379,15 @@ impl Component for AgentOwned {
                         if on_hand.is_some() {
                             board.cache.insert_on_hand(agent.0, entity);
                         }
+                        if deck.is_some() {
+                            board.cache.insert_deck(agent.0, entity);
+                        }
+                        if on_graveyard.is_some() {
+                            board.cache.insert_on_graveyard(agent.0, entity);
+                        }
+                        if on_exile.is_some() {
+                            board.cache.insert_on_exile(agent.0, entity);
+                        }
                     }
                 }
             }
@@ -286,6 +397,9 @@ impl Component for AgentOwned {
             let board_entity = world.get::<OnBoard>(entity).cloned();
             let agent = world.get::<AgentOwned>(entity).cloned();
             let on_hand = world.get::<OnHand>(entity).cloned();
+            let deck = world.get::<BoardDeck>(entity).cloned();
+            let on_graveyard = world.get::<OnGraveyard>(entity).cloned();
+            let on_exile = world.get::<OnExile>(entity).cloned();
 
             if let Some(board_entity) = board_entity {
                 if let Some(mut board) = world.get_mut::<Board>(board_entity.0) {
@@ -294,6 +408,15 @@ impl Component for AgentOwned {
                         if on_hand.is_some() {
                             board.cache.remove_from_hand(agent.0, &entity);
                         }
+                        if deck.is_some() {
+                            board.cache.remove_deck(agent.0, &entity);
+                        }
+                        if on_graveyard.is_some() {
+                            board.cache.remove_from_graveyard(agent.0, &entity);
+                        }
+                        if on_exile.is_some() {
+                            board.cache.remove_from_exile(agent.0, &entity);
+                        }
                     }
                 }
             }
@@ -301,24 +424,37 @@ impl Component for AgentOwned {
     }
 }
 
+/// The client controlling the agent, None if the agent is not controlled by a client
+pub(crate) fn agent_client_id(world: &World, agent: Entity) -> Option<ClientId> {
+    world
+        .get_resource::<AgentManager>()
+        .and_then(|agent_manager| agent_manager.get_auth_id(&agent))
+        .and_then(|auth_id| {
+            world
+                .get_resource::<AuthManager>()
+                .and_then(|auth_manager| auth_manager.get_client_id(auth_id))
+        })
+        .copied()
+}
+
 impl MapEntities for AgentOwned {
     fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
         self.0 = entity_mapper.map_entity(self.0);
     }
 }
 
+/// An agent leaving (ex: client disconnection) lose the match of every board he was playing on
 pub(crate) fn board_agent_removed_observer(
     //TODO do the same for slot ?
-    //TODO check if only one player and win/draw by default
-    //TODO only on agent on board ? idk
     trigger: Trigger<OnRemove, Agent>,
-    query: Query<&OnBoard>,
     mut boards: Query<&mut Board>,
 ) {
-    //TODO err print
-    if let Ok(on_board) = query.get(trigger.entity()) {
-        if let Ok(mut board) = boards.get_mut(on_board.0) {
+    for mut board in boards.iter_mut() {
+        if board.state.agents.contains(&trigger.entity()) {
             board.cache.clean_agent_associate_values(trigger.entity());
+            board
+                .state
+                .defeat_agent(trigger.entity(), DefeatReason::Disconnect);
         }
     }
 }
@@ -0,0 +1,119 @@
+use bevy::{ecs::entity::MapEntities, prelude::*};
+use bevy_replicon::prelude::{FromClient, SendMode, ToClients};
+use epithet::{agent::AgentManager, net::AuthManager};
+use serde::{Deserialize, Serialize};
+
+use crate::{ActionRejectedPacket, Board, DrawCommand, RejectionReason, TurnAction};
+
+/// Request from a client to draw a card from his deck
+#[derive(Event, Serialize, Deserialize, Clone, Debug)]
+pub struct AgentDrawPacket {
+    pub board: Entity,
+}
+
+impl AgentDrawPacket {
+    pub fn new(board: Entity) -> Self {
+        Self { board }
+    }
+}
+
+impl MapEntities for AgentDrawPacket {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.board = entity_mapper.map_entity(self.board);
+    }
+}
+
+/// Why a draw requested by a client got refused, the turn limit is a [`RejectionReason::TurnLimit`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum DrawError {
+    NoAgent,
+    UnknownBoard,
+    BoardFinished,
+    NotTurnAgent,
+    NoDeck,
+}
+
+pub(crate) fn draw_packet_system(
+    mut commands: Commands,
+    mut packets: EventReader<FromClient<AgentDrawPacket>>,
+    auth_manager: Res<AuthManager>,
+    agent_manager: Res<AgentManager>,
+    mut boards: Query<&mut Board>,
+    mut rejected: EventWriter<ToClients<ActionRejectedPacket>>,
+) {
+    for FromClient { client_id, event } in packets.read() {
+        let mut reject = |reason: RejectionReason| {
+            rejected.send(ToClients {
+                mode: SendMode::Direct(*client_id),
+                event: ActionRejectedPacket::new(event.board, reason),
+            });
+        };
+
+        let agent = match agent_manager.agent_from_client_id(client_id, &auth_manager) {
+            Some(agent) => agent,
+            None => {
+                warn!(
+                    "Client {:?} tried to draw without having an agent",
+                    client_id
+                );
+                reject(RejectionReason::Draw(DrawError::NoAgent));
+                continue;
+            }
+        };
+
+        let mut board = match boards.get_mut(event.board) {
+            Ok(board) => board,
+            Err(_) => {
+                warn!(
+                    "Client {:?} tried to draw on a board {:?} that does not exist",
+                    client_id, event.board
+                );
+                reject(RejectionReason::Draw(DrawError::UnknownBoard));
+                continue;
+            }
+        };
+
+        if board.state.is_finished() {
+            warn!(
+                "Client {:?} tried to draw on the finished board {:?}",
+                client_id, event.board
+            );
+            reject(RejectionReason::Draw(DrawError::BoardFinished));
+            continue;
+        }
+
+        if !board
+            .state
+            .get_current_turn_agent()
+            .map_or(false, |current_agent| current_agent == *agent)
+        {
+            warn!(
+                "Client {:?} tried to draw without being the current turn agent on the board {:?}",
+                client_id, event.board
+            );
+            reject(RejectionReason::Draw(DrawError::NotTurnAgent));
+            continue;
+        }
+
+        if board.cache.get_deck(agent).is_none() {
+            warn!(
+                "Client {:?} tried to draw without having a deck on the board {:?}",
+                client_id, event.board
+            );
+            reject(RejectionReason::Draw(DrawError::NoDeck));
+            continue;
+        }
+
+        if let Err(reason) = board.state.can_perform(TurnAction::Draw) {
+            warn!(
+                "Client {:?} tried to draw on the board {:?} but was rejected: {:?}",
+                client_id, event.board, reason
+            );
+            reject(reason);
+            continue;
+        }
+
+        board.state.record_action(TurnAction::Draw);
+        commands.add(DrawCommand::new(event.board, *agent, 1));
+    }
+}
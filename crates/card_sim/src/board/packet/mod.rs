@@ -1,14 +1,24 @@
+mod draw;
 mod join;
+mod priority;
+mod rejection;
+mod result;
 mod stage;
 mod summon;
+mod target;
 
+pub use draw::*;
 pub use join::*;
+pub use priority::*;
+pub use rejection::*;
+pub use result::*;
 pub use stage::*;
 pub use summon::*;
+pub use target::*;
 
 use bevy::prelude::*;
 use bevy_replicon::prelude::{
-    server_or_singleplayer, ChannelKind, ClientEventAppExt, ServerEventAppExt,
+    client_connected, server_or_singleplayer, ChannelKind, ClientEventAppExt, ServerEventAppExt,
 };
 
 pub(crate) fn board_packet_plugin(app: &mut App) {
@@ -16,12 +26,40 @@ pub(crate) fn board_packet_plugin(app: &mut App) {
     app.add_mapped_server_event::<ClientJoinedBoardPacket>(ChannelKind::Ordered);
     app.add_mapped_client_event::<AgentSummonEvent>(ChannelKind::Ordered);
     app.add_mapped_client_event::<StageChangePacket>(ChannelKind::Ordered);
+    app.add_mapped_client_event::<AgentDrawPacket>(ChannelKind::Ordered);
+    app.add_mapped_client_event::<ConcedePacket>(ChannelKind::Ordered);
+    app.add_mapped_server_event::<MatchResultPacket>(ChannelKind::Ordered);
+    app.add_mapped_server_event::<StageChangedPacket>(ChannelKind::Ordered);
+    app.add_mapped_server_event::<ActionRejectedPacket>(ChannelKind::Ordered);
+    app.add_mapped_server_event::<PriorityPacket>(ChannelKind::Ordered);
+    app.add_mapped_client_event::<PassPriorityPacket>(ChannelKind::Ordered);
+    app.add_mapped_client_event::<ChainRespondPacket>(ChannelKind::Ordered);
+    app.add_mapped_client_event::<TargetResponsePacket>(ChannelKind::Ordered);
 
     app.add_systems(
         Update,
-        (summon_packet_system, stage_client_stage_packet_system).run_if(server_or_singleplayer),
+        (
+            summon_packet_system,
+            stage_client_stage_packet_system,
+            draw_packet_system,
+            concede_packet_system,
+            pass_priority_packet_system,
+            chain_respond_packet_system,
+            target_response_packet_system,
+        )
+            .run_if(server_or_singleplayer),
     );
 
     app.add_systems(Update, player_join_packet_system);
     app.add_systems(Update, player_joined_packet_system);
+    // They write the board state, the server already has the authoritative one
+    app.add_systems(
+        Update,
+        (match_result_packet_system, stage_changed_packet_system).run_if(client_connected),
+    );
+    app.add_systems(Update, action_rejected_packet_system);
+    app.add_systems(Update, priority_packet_system);
+
+    app.observe(stage_entered_packet_observer);
+    app.observe(priority_given_packet_observer);
 }
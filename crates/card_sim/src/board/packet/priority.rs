@@ -0,0 +1,253 @@
+use bevy::{ecs::entity::MapEntities, prelude::*};
+use bevy_replicon::prelude::{FromClient, SendMode, ToClients};
+use epithet::{agent::AgentManager, net::AuthManager};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ActionRejectedPacket, ActivateEffectCommand, Board, ChainError, ChainLink, Effects,
+    PassPriorityCommand, PriorityGiven, RejectionReason,
+};
+
+/// Packet sent by the server to the client whose agent got the priority on the chain of a board
+#[derive(Event, Serialize, Deserialize, Clone, Debug)]
+pub struct PriorityPacket {
+    pub board: Entity,
+    pub agent: Entity,
+    /// The link the agent can respond to
+    pub link: ChainLink,
+    pub chain_len: usize,
+    /// Seconds before the server pass automatically
+    pub timeout: f32,
+}
+
+impl MapEntities for PriorityPacket {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.board = entity_mapper.map_entity(self.board);
+        self.agent = entity_mapper.map_entity(self.agent);
+        self.link.map_entities(entity_mapper);
+    }
+}
+
+/// Request from a client to pass its priority on the chain of a board
+#[derive(Event, Serialize, Deserialize, Clone, Debug)]
+pub struct PassPriorityPacket {
+    pub board: Entity,
+}
+
+impl PassPriorityPacket {
+    pub fn new(board: Entity) -> Self {
+        Self { board }
+    }
+}
+
+impl MapEntities for PassPriorityPacket {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.board = entity_mapper.map_entity(self.board);
+    }
+}
+
+/// Request from a client to activate an effect of one of his cards
+/// It respond to the chain when one is open, otherwise it start a new chain
+#[derive(Event, Serialize, Deserialize, Clone, Debug)]
+pub struct ChainRespondPacket {
+    pub board: Entity,
+    pub card: Entity,
+    pub effect_index: usize,
+}
+
+impl ChainRespondPacket {
+    pub fn new(board: Entity, card: Entity, effect_index: usize) -> Self {
+        Self {
+            board,
+            card,
+            effect_index,
+        }
+    }
+}
+
+impl MapEntities for ChainRespondPacket {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.board = entity_mapper.map_entity(self.board);
+        self.card = entity_mapper.map_entity(self.card);
+    }
+}
+
+pub(crate) fn priority_given_packet_observer(
+    trigger: Trigger<PriorityGiven>,
+    boards: Query<&Board>,
+    auth_manager: Res<AuthManager>,
+    agent_manager: Res<AgentManager>,
+    mut writer: EventWriter<ToClients<PriorityPacket>>,
+) {
+    let event = trigger.event();
+
+    let Ok(board) = boards.get(event.board) else {
+        return;
+    };
+    let Some(tree) = board.state.get_chain() else {
+        return;
+    };
+    let Some(link) = tree.last_link() else {
+        return;
+    };
+
+    let client_id = agent_manager
+        .get_auth_id(&event.agent)
+        .and_then(|auth_id| auth_manager.get_client_id(auth_id));
+
+    if let Some(client_id) = client_id {
+        writer.send(ToClients {
+            mode: SendMode::Direct(*client_id),
+            event: PriorityPacket {
+                board: event.board,
+                agent: event.agent,
+                link: *link,
+                chain_len: tree.len(),
+                timeout: board.state.rules.priority_timeout,
+            },
+        });
+    }
+}
+
+pub(crate) fn pass_priority_packet_system(
+    mut commands: Commands,
+    mut packets: EventReader<FromClient<PassPriorityPacket>>,
+    auth_manager: Res<AuthManager>,
+    agent_manager: Res<AgentManager>,
+    boards: Query<&Board>,
+    mut rejected: EventWriter<ToClients<ActionRejectedPacket>>,
+) {
+    for FromClient { client_id, event } in packets.read() {
+        let agent = match agent_manager.agent_from_client_id(client_id, &auth_manager) {
+            Some(agent) => agent,
+            None => {
+                warn!(
+                    "Client {:?} tried to pass priority without having an agent",
+                    client_id
+                );
+                continue;
+            }
+        };
+
+        let board = match boards.get(event.board) {
+            Ok(board) => board,
+            Err(_) => {
+                warn!(
+                    "Client {:?} tried to pass priority on a board {:?} that does not exist",
+                    client_id, event.board
+                );
+                continue;
+            }
+        };
+
+        let error = match board.state.get_priority() {
+            None => Some(ChainError::NoChain),
+            Some(priority) if priority != *agent => Some(ChainError::NoPriority),
+            _ => None,
+        };
+
+        if let Some(error) = error {
+            warn!(
+                "Client {:?} tried to pass priority on the board {:?}: {}",
+                client_id, event.board, error
+            );
+            rejected.send(ToClients {
+                mode: SendMode::Direct(*client_id),
+                event: ActionRejectedPacket::new(event.board, RejectionReason::Chain(error)),
+            });
+            continue;
+        }
+
+        commands.add(PassPriorityCommand::new(event.board, *agent));
+    }
+}
+
+pub(crate) fn chain_respond_packet_system(
+    mut commands: Commands,
+    mut packets: EventReader<FromClient<ChainRespondPacket>>,
+    auth_manager: Res<AuthManager>,
+    agent_manager: Res<AgentManager>,
+    boards: Query<&Board>,
+    effects: Query<&Effects>,
+    mut rejected: EventWriter<ToClients<ActionRejectedPacket>>,
+) {
+    for FromClient { client_id, event } in packets.read() {
+        let agent = match agent_manager.agent_from_client_id(client_id, &auth_manager) {
+            Some(agent) => agent,
+            None => {
+                warn!(
+                    "Client {:?} tried to activate an effect without having an agent",
+                    client_id
+                );
+                continue;
+            }
+        };
+
+        let board = match boards.get(event.board) {
+            Ok(board) => board,
+            Err(_) => {
+                warn!(
+                    "Client {:?} tried to activate an effect on a board {:?} that does not exist",
+                    client_id, event.board
+                );
+                continue;
+            }
+        };
+
+        let result = effects
+            .get(event.card)
+            .ok()
+            .and_then(|effects| effects.get_effect(event.effect_index))
+            .ok_or(ChainError::UnknownEffect {
+                effect_index: event.effect_index,
+            })
+            .and_then(|effect| {
+                board
+                    .state
+                    .can_activate_effect(*agent, effect.get_effect_speed())
+            });
+
+        if let Err(error) = result {
+            warn!(
+                "Client {:?} tried to activate an effect on the board {:?}: {}",
+                client_id, event.board, error
+            );
+            rejected.send(ToClients {
+                mode: SendMode::Direct(*client_id),
+                event: ActionRejectedPacket::new(event.board, RejectionReason::Chain(error)),
+            });
+            continue;
+        }
+
+        // The card side is checked by the command as it need the world
+        commands.add(ActivateEffectCommand::new(
+            event.board,
+            *agent,
+            event.card,
+            event.effect_index,
+        ));
+    }
+}
+
+/// Triggered on the client when its agent got the priority on the chain of a board
+#[derive(Event, Clone, Debug)]
+pub struct ClientPriorityGiven {
+    pub board: Entity,
+    pub agent: Entity,
+    pub link: ChainLink,
+    pub chain_len: usize,
+    pub timeout: f32,
+}
+
+// RepliconObserver
+pub fn priority_packet_system(mut commands: Commands, mut packets: EventReader<PriorityPacket>) {
+    for packet in packets.read() {
+        commands.trigger(ClientPriorityGiven {
+            board: packet.board,
+            agent: packet.agent,
+            link: packet.link,
+            chain_len: packet.chain_len,
+            timeout: packet.timeout,
+        });
+    }
+}
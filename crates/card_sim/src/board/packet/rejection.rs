@@ -0,0 +1,72 @@
+use bevy::{ecs::entity::MapEntities, prelude::*};
+use bevy_replicon::prelude::{SendMode, ToClients};
+use serde::{Deserialize, Serialize};
+
+use crate::{agent_client_id, ChainError, DrawError, TargetSelectionError, TurnAction};
+
+/// Why the server refused an action requested by a client
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum RejectionReason {
+    TurnLimit { action: TurnAction, limit: u32 },
+    Chain(ChainError),
+    Targets(TargetSelectionError),
+    Draw(DrawError),
+}
+
+/// Packet sent by the server to the client whose action got refused
+#[derive(Event, Serialize, Deserialize, Clone, Debug)]
+pub struct ActionRejectedPacket {
+    pub board: Entity,
+    pub reason: RejectionReason,
+}
+
+impl ActionRejectedPacket {
+    pub fn new(board: Entity, reason: RejectionReason) -> Self {
+        Self { board, reason }
+    }
+}
+
+impl MapEntities for ActionRejectedPacket {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.board = entity_mapper.map_entity(self.board);
+    }
+}
+
+/// Send the rejection to the client controlling the agent, for commands that can't return their errors
+pub(crate) fn send_rejection(
+    world: &mut World,
+    board: Entity,
+    agent: Entity,
+    reason: RejectionReason,
+) {
+    if let Some(client_id) = agent_client_id(world, agent) {
+        world.send_event(ToClients {
+            mode: SendMode::Direct(client_id),
+            event: ActionRejectedPacket::new(board, reason),
+        });
+    }
+}
+
+/// Triggered on the client when the server refused one of its actions
+#[derive(Event, Clone, Debug)]
+pub struct ClientActionRejected {
+    pub board: Entity,
+    pub reason: RejectionReason,
+}
+
+// RepliconObserver
+pub fn action_rejected_packet_system(
+    mut commands: Commands,
+    mut packets: EventReader<ActionRejectedPacket>,
+) {
+    for packet in packets.read() {
+        warn!(
+            "The server rejected an action on the board {:?}: {:?}",
+            packet.board, packet.reason
+        );
+        commands.trigger(ClientActionRejected {
+            board: packet.board,
+            reason: packet.reason.clone(),
+        });
+    }
+}
@@ -0,0 +1,89 @@
+use bevy::{ecs::entity::MapEntities, prelude::*};
+use bevy_replicon::prelude::FromClient;
+use epithet::{agent::AgentManager, net::AuthManager};
+use serde::{Deserialize, Serialize};
+
+use crate::{Board, BoardGameState, DefeatReason, MatchResult};
+
+/// Request from a client to concede the match of a board
+#[derive(Event, Serialize, Deserialize, Clone, Debug)]
+pub struct ConcedePacket {
+    pub board: Entity,
+}
+
+impl ConcedePacket {
+    pub fn new(board: Entity) -> Self {
+        Self { board }
+    }
+}
+
+impl MapEntities for ConcedePacket {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.board = entity_mapper.map_entity(self.board);
+    }
+}
+
+pub(crate) fn concede_packet_system(
+    mut packets: EventReader<FromClient<ConcedePacket>>,
+    auth_manager: Res<AuthManager>,
+    agent_manager: Res<AgentManager>,
+    mut boards: Query<&mut Board>,
+) {
+    for FromClient { client_id, event } in packets.read() {
+        let agent = match agent_manager.agent_from_client_id(client_id, &auth_manager) {
+            Some(agent) => agent,
+            None => {
+                warn!(
+                    "Client {:?} tried to concede without having an agent",
+                    client_id
+                );
+                continue;
+            }
+        };
+
+        if let Ok(mut board) = boards.get_mut(event.board) {
+            board.state.defeat_agent(*agent, DefeatReason::Concede);
+        } else {
+            warn!(
+                "Client {:?} tried to concede on a board {:?} that does not exist",
+                client_id, event.board
+            );
+        }
+    }
+}
+
+/// Packet broadcasted by the server when the match of a board ends
+#[derive(Event, Serialize, Deserialize, Clone, Debug)]
+pub struct MatchResultPacket {
+    pub board: Entity,
+    pub result: MatchResult,
+}
+
+impl MatchResultPacket {
+    pub fn new(board: Entity, result: MatchResult) -> Self {
+        Self { board, result }
+    }
+}
+
+impl MapEntities for MatchResultPacket {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.board = entity_mapper.map_entity(self.board);
+        self.result.map_entities(entity_mapper);
+    }
+}
+
+// RepliconObserver
+pub fn match_result_packet_system(
+    mut packets: EventReader<MatchResultPacket>,
+    mut boards: Query<&mut Board>,
+) {
+    for packet in packets.read() {
+        if let Ok(mut board) = boards.get_mut(packet.board) {
+            board.state.game_state = BoardGameState::Finished {
+                result: packet.result,
+            };
+        } else {
+            warn!("Server sent a match result for a board that does not exist, this should not be possible");
+        }
+    }
+}
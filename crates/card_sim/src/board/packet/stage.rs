@@ -1,9 +1,13 @@
 use bevy::{ecs::entity::MapEntities, prelude::*};
-use bevy_replicon::prelude::FromClient;
+use bevy_replicon::prelude::{
+    server_or_singleplayer, FromClient, RepliconClient, SendMode, ToClients,
+};
 use epithet::{agent::AgentManager, net::AuthManager};
 use serde::{Deserialize, Serialize};
 
-use crate::{Board, BoardStage};
+use crate::{
+    trigger_stage_transitions, ActionRejectedPacket, Board, BoardStage, StageEntered, TurnAction,
+};
 
 #[derive(Event, Serialize, Deserialize, Clone)]
 pub struct StageChangePacket {
@@ -24,26 +28,60 @@ impl MapEntities for StageChangePacket {
 }
 
 pub(crate) fn stage_client_stage_packet_system(
+    mut commands: Commands,
     mut stage_client_stage_packet: EventReader<FromClient<StageChangePacket>>,
     auth_manager: Res<AuthManager>,
     agent_manager: Res<AgentManager>,
     mut boards: Query<&mut Board>,
+    mut rejected: EventWriter<ToClients<ActionRejectedPacket>>,
 ) {
     let auth_manager = auth_manager.into_inner();
 
     for FromClient { client_id, event } in stage_client_stage_packet.read() {
         if let Some(agent) = agent_manager.agent_from_client_id(client_id, auth_manager) {
             if let Ok(mut board) = boards.get_mut(event.board) {
+                if board.state.is_finished() {
+                    warn!(
+                        "Client {:?} tried to change stage on the finished board {:?}",
+                        client_id, event.board
+                    );
+                    continue;
+                }
                 if board
                     .state
                     .get_current_turn_agent()
                     .map_or(false, |current_agent| current_agent == *agent)
                 {
-                    if board.state.advance_stage(event.stage.clone()) {
-                        println!("Client {:?} changed stage to {:?}", client_id, event.stage);
-                        //TODO send stage change to all clients
-                    } else {
-                        warn!("Client {:?} tried to change stage to {:?} but it was a invalid stage target", client_id, event.stage);
+                    if board.state.get_chain().is_some() {
+                        warn!("Client {:?} tried to change stage while a chain is resolving on the board {:?}", client_id, event.board);
+                        continue;
+                    }
+
+                    let skip = board.state.is_stage_skip(&event.stage);
+
+                    if skip {
+                        if let Err(reason) = board.state.can_perform(TurnAction::StageSkip) {
+                            warn!("Client {:?} tried to skip to the stage {:?} but was rejected: {:?}", client_id, event.stage, reason);
+                            rejected.send(ToClients {
+                                mode: SendMode::Direct(*client_id),
+                                event: ActionRejectedPacket::new(event.board, reason),
+                            });
+                            continue;
+                        }
+                    }
+
+                    match board.state.advance_stage(event.stage.clone()) {
+                        Ok(transitions) => {
+                            // Counted after the change so the next turn counters are not touched when the skip ends the turn
+                            if skip && !board.state.is_turn_start(&event.stage) {
+                                board.state.record_action(TurnAction::StageSkip);
+                            }
+                            info!("Client {:?} changed stage to {:?}", client_id, event.stage);
+                            trigger_stage_transitions(&mut commands, event.board, transitions);
+                        }
+                        Err(e) => {
+                            warn!("Client {:?} tried to change stage to {:?} but it was a invalid stage target: {}", client_id, event.stage, e);
+                        }
                     }
                 } else {
                     warn!("Client {:?} tried to change stage while not being the current turn agent of the board {:?}", client_id, event.board);
@@ -57,3 +95,123 @@ pub(crate) fn stage_client_stage_packet_system(
         }
     }
 }
+
+/// Packet broadcasted by the server for every stage entered on a board
+/// The board state is also replicated, this packet exist so clients can react to each stage in order even when a change goes through multiple stages at once
+#[derive(Event, Serialize, Deserialize, Clone, Debug)]
+pub struct StageChangedPacket {
+    pub board: Entity,
+    pub stage: BoardStage,
+    pub turn_agent: Entity,
+    pub turn: u32,
+}
+
+impl MapEntities for StageChangedPacket {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.board = entity_mapper.map_entity(self.board);
+        self.turn_agent = entity_mapper.map_entity(self.turn_agent);
+    }
+}
+
+/// The host of a listen server or a singleplayer game doesn't receive its own packets, it gets the client events from here
+pub(crate) fn stage_entered_packet_observer(
+    trigger: Trigger<StageEntered>,
+    mut commands: Commands,
+    mut writer: EventWriter<ToClients<StageChangedPacket>>,
+    boards: Query<&Board>,
+    client: Option<Res<RepliconClient>>,
+) {
+    let event = trigger.event();
+
+    if server_or_singleplayer(client) {
+        if let Ok(board) = boards.get(event.board) {
+            trigger_client_stage_events(
+                &mut commands,
+                board,
+                event.board,
+                &event.stage,
+                event.agent,
+                event.turn,
+            );
+        }
+    }
+
+    writer.send(ToClients {
+        mode: SendMode::Broadcast,
+        event: StageChangedPacket {
+            board: event.board,
+            stage: event.stage.clone(),
+            turn_agent: event.agent,
+            turn: event.turn,
+        },
+    });
+}
+
+/// Triggered on the clients and on the host when the server changed the stage of a board
+#[derive(Event, Clone, Debug)]
+pub struct ClientStageChanged {
+    pub board: Entity,
+    pub stage: BoardStage,
+    pub turn_agent: Entity,
+    pub turn: u32,
+}
+
+/// Triggered on the client or the host when the turn of its own agent starts on a board
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ClientTurnStarted {
+    pub board: Entity,
+    pub agent: Entity,
+    pub turn: u32,
+}
+
+// RepliconObserver
+pub fn stage_changed_packet_system(
+    mut commands: Commands,
+    mut packets: EventReader<StageChangedPacket>,
+    mut boards: Query<&mut Board>,
+) {
+    for packet in packets.read() {
+        let Ok(mut board) = boards.get_mut(packet.board) else {
+            warn!("Server sent a stage change for a board that does not exist, this should not be possible");
+            continue;
+        };
+
+        // Apply it right away so observers don't read a board state older than the event they received
+        board.state.stage = packet.stage.clone();
+        board.state.current_turn_agent = Some(packet.turn_agent);
+        board.state.turn = packet.turn;
+
+        trigger_client_stage_events(
+            &mut commands,
+            &board,
+            packet.board,
+            &packet.stage,
+            packet.turn_agent,
+            packet.turn,
+        );
+    }
+}
+
+fn trigger_client_stage_events(
+    commands: &mut Commands,
+    board: &Board,
+    board_entity: Entity,
+    stage: &BoardStage,
+    turn_agent: Entity,
+    turn: u32,
+) {
+    commands.trigger(ClientStageChanged {
+        board: board_entity,
+        stage: stage.clone(),
+        turn_agent,
+        turn,
+    });
+
+    if board.state.is_turn_start(stage) && board.client_is_on_board == Some(turn_agent) {
+        commands.trigger(ClientTurnStarted {
+            board: board_entity,
+            agent: turn_agent,
+            turn,
+        });
+    }
+}
@@ -5,8 +5,7 @@ use epithet::{agent::AgentManager, net::AuthManager};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    AgentActionPacket, AgentActionRegistry, AgentOwned, Board, BoardSlot, OnHand, OnSlot,
-    TargetAgentAction,
+    ActionRejectedPacket, AgentOwned, Board, BoardSlot, CardSummoned, OnHand, OnSlot, TurnAction,
 };
 
 //TODO add controller interdediate as this is a trust the client event
@@ -39,13 +38,12 @@ impl MapEntities for AgentSummonEvent {
 pub(crate) fn summon_packet_system(
     mut commands: Commands,
     mut events: EventReader<FromClient<AgentSummonEvent>>,
-    boards: Query<&mut Board>,
+    mut boards: Query<&mut Board>,
     slots: Query<&mut BoardSlot>,
     on_hands: Query<&AgentOwned, With<OnHand>>,
     auth_manager: Res<AuthManager>,
     agent_manager: Res<AgentManager>,
-    mut test_action: EventWriter<ToClients<AgentActionPacket>>,
-    action_registry: Res<AgentActionRegistry>,
+    mut rejected: EventWriter<ToClients<ActionRejectedPacket>>,
 ) {
     for FromClient { client_id, event } in events.read() {
         let agent = match agent_manager.agent_from_client_id(client_id, &auth_manager) {
@@ -75,11 +73,19 @@ pub(crate) fn summon_packet_system(
             continue;
         }
 
-        let board = match boards.get(event.board_entity) {
+        let mut board = match boards.get_mut(event.board_entity) {
             Ok(board) => board,
             Err(_) => continue,
         };
 
+        if board.state.is_finished() {
+            warn!(
+                "Client {:?} tried to summon on the finished board {:?}",
+                client_id, event.board_entity
+            );
+            continue;
+        }
+
         //TODO change later as you can summon without being the turn agent in the future, prio or smth like that ?
         if !board
             .state
@@ -90,6 +96,18 @@ pub(crate) fn summon_packet_system(
             continue;
         }
 
+        if let Err(reason) = board.state.can_perform(TurnAction::Summon) {
+            warn!(
+                "Client {:?} tried to summon on the board {:?} but was rejected: {:?}",
+                client_id, event.board_entity, reason
+            );
+            rejected.send(ToClients {
+                mode: SendMode::Direct(*client_id),
+                event: ActionRejectedPacket::new(event.board_entity, reason),
+            });
+            continue;
+        }
+
         if !slots.contains(event.slot_entity) || !board.cache.is_on_field(event.slot_entity) {
             warn!("Client {:?} tried to summon to a slot {:?} that does not exist or is not on the field on the board {:?}", client_id, event.slot_entity,  event.board_entity);
             continue;
@@ -98,16 +116,12 @@ pub(crate) fn summon_packet_system(
         if let Some(mut summoned_entity) = commands.get_entity(event.card_entity) {
             summoned_entity.remove::<OnHand>();
             summoned_entity.insert(OnSlot(event.slot_entity));
-            test_action.send(ToClients {
-                mode: SendMode::Direct(*client_id),
-                event: AgentActionPacket::from(
-                    *agent,
-                    event.board_entity,
-                    TargetAgentAction {},
-                    *action_registry
-                        .get_action_id::<TargetAgentAction>()
-                        .unwrap(),
-                ),
+            board.state.record_action(TurnAction::Summon);
+            commands.trigger(CardSummoned {
+                board: event.board_entity,
+                card: event.card_entity,
+                agent: *agent,
+                slot: event.slot_entity,
             });
         } else {
             warn!("Client {:?} tried to summon a card that does not exist, on slot {:?}, on the board {:?}", client_id, event.slot_entity, event.board_entity);
@@ -0,0 +1,82 @@
+use bevy::{ecs::entity::MapEntities, prelude::*};
+use bevy_replicon::prelude::FromClient;
+use epithet::{agent::AgentManager, net::AuthManager};
+use serde::{Deserialize, Serialize};
+
+use crate::{CancelSelectionCommand, SelectTargetsCommand};
+
+/// Targets chosen by a client for a [`crate::TargetAgentAction`] request
+#[derive(Event, Serialize, Deserialize, Clone, Debug)]
+pub struct TargetResponsePacket {
+    pub board: Entity,
+    pub request: u32,
+    pub targets: Vec<Entity>,
+    /// Give up the selection instead, only the costs of an activation can be cancelled
+    pub cancel: bool,
+}
+
+impl TargetResponsePacket {
+    pub fn new(board: Entity, request: u32, targets: Vec<Entity>) -> Self {
+        Self {
+            board,
+            request,
+            targets,
+            cancel: false,
+        }
+    }
+
+    pub fn cancel(board: Entity, request: u32) -> Self {
+        Self {
+            board,
+            request,
+            targets: Vec::new(),
+            cancel: true,
+        }
+    }
+}
+
+impl MapEntities for TargetResponsePacket {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.board = entity_mapper.map_entity(self.board);
+        for target in self.targets.iter_mut() {
+            *target = entity_mapper.map_entity(*target);
+        }
+    }
+}
+
+pub(crate) fn target_response_packet_system(
+    mut commands: Commands,
+    mut packets: EventReader<FromClient<TargetResponsePacket>>,
+    auth_manager: Res<AuthManager>,
+    agent_manager: Res<AgentManager>,
+) {
+    for FromClient { client_id, event } in packets.read() {
+        let agent = match agent_manager.agent_from_client_id(client_id, &auth_manager) {
+            Some(agent) => agent,
+            None => {
+                warn!(
+                    "Client {:?} tried to choose targets without having an agent",
+                    client_id
+                );
+                continue;
+            }
+        };
+
+        if event.cancel {
+            commands.add(CancelSelectionCommand {
+                board: event.board,
+                agent,
+                request: event.request,
+            });
+            continue;
+        }
+
+        // The selection is checked against the pending request by the command
+        commands.add(SelectTargetsCommand {
+            board: event.board,
+            agent,
+            request: event.request,
+            targets: event.targets.clone(),
+        });
+    }
+}
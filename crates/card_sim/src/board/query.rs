@@ -1,41 +1,162 @@
-use bevy::math::IVec3;
+use bevy::{ecs::entity::EntityHashSet, math::IVec3, prelude::*};
+use smallvec::SmallVec;
 
-use crate::{AgentOwned, Board};
+use crate::{AgentOwned, Board, RuntimeQueryExt, RuntimeQueryTag};
 
+/// A zone of the board to look entities up from, the lookups are done through the [`crate::BoardCache`]
+#[derive(Clone, Copy, Debug)]
 pub enum BoardQueryLoc {
     All,
     Deck(AgentOwned),
     Hand(AgentOwned),
-    Field,
+    Graveyard(AgentOwned),
+    Exile(AgentOwned),
+    /// The field entities and the entities on the slots, only of the given agent if any
+    Field(Option<AgentOwned>),
     OnSlot(IVec3),
 }
 
+/// Most queries return a handful of entities (a hand, a slot etc) so they are kept inline
+pub type BoardQueryResult = SmallVec<[Entity; 8]>;
+
 pub struct BoardQuery;
 
 impl BoardQuery {
-    pub fn query(board: &Board, locations: Vec<BoardQueryLoc>) {
-        let mut entities = vec![];
+    /// Get the entities in the given locations, an entity present in multiple locations is only returned once
+    /// The entities are returned in the order of the locations then in the order of each lookup (ex: graveyard order)
+    pub fn query(board: &Board, locations: &[BoardQueryLoc]) -> BoardQueryResult {
+        let mut entities = BoardQueryResult::new();
+        let mut seen = EntityHashSet::default();
+        let mut push = |entity: Entity| {
+            if seen.insert(entity) {
+                entities.push(entity);
+            }
+        };
 
         for location in locations {
             match location {
                 BoardQueryLoc::All => {
-                    entities.extend(board.cache.get_entities().iter());
+                    board.cache.get_entities().iter().for_each(|e| push(*e));
                 }
                 BoardQueryLoc::Deck(agent) => {
-                    //entities.push(vec);
+                    if let Some(deck) = board.cache.get_deck(&agent.0) {
+                        push(*deck);
+                    }
                 }
                 BoardQueryLoc::Hand(player) => {
                     if let Some(hand) = board.cache.get_by_hand(&player.0) {
-                        entities.extend(hand.iter());
+                        hand.iter().for_each(|e| push(*e));
+                    }
+                }
+                BoardQueryLoc::Graveyard(player) => {
+                    if let Some(graveyard) = board.cache.get_by_graveyard(&player.0) {
+                        graveyard.iter().for_each(|e| push(*e));
+                    }
+                }
+                BoardQueryLoc::Exile(player) => {
+                    if let Some(exile) = board.cache.get_by_exile(&player.0) {
+                        exile.iter().for_each(|e| push(*e));
                     }
                 }
-                BoardQueryLoc::Field => {}
+                BoardQueryLoc::Field(player) => {
+                    let agent_entities =
+                        player.and_then(|player| board.cache.get_by_agent(player.0));
+
+                    board
+                        .cache
+                        .get_entities_on_field()
+                        .iter()
+                        .chain(board.cache.get_entities_on_slots())
+                        .filter(|e| {
+                            player.is_none()
+                                || agent_entities.map_or(false, |entities| entities.contains(*e))
+                        })
+                        .for_each(|e| push(*e));
+                }
                 BoardQueryLoc::OnSlot(pos) => {
-                    if let Some(entity) = board.cache.get_on_slot(&pos) {
-                        entities.push(entity);
+                    if let Some(entity) = board.cache.get_slot_occupant(pos) {
+                        push(*entity);
                     }
                 }
             }
         }
+        entities
+    }
+
+    /// Same as [`BoardQuery::query`] but only keep the entities matching the tags
+    /// Fails if the board does not exist or if a tag is not registered
+    pub fn query_with_tags(
+        world: &mut World,
+        board_entity: Entity,
+        locations: &[BoardQueryLoc],
+        tags: &[RuntimeQueryTag],
+    ) -> Result<BoardQueryResult, String> {
+        let mut entities = match world.get::<Board>(board_entity) {
+            Some(board) => Self::query(board, locations),
+            None => return Err(format!("Board {:?} does not exist", board_entity)),
+        };
+
+        if tags.is_empty() {
+            return Ok(entities);
+        }
+
+        let mut query_state = world
+            .query_runtime_tag::<Entity, ()>(tags)
+            .map_err(|e| e.to_string())?
+            .build();
+
+        entities.retain(|entity| query_state.get(world, *entity).is_ok());
+        Ok(entities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_and_slot_entities_are_returned_once() {
+        let agent = Entity::from_raw(1);
+        let card = Entity::from_raw(2);
+        let other = Entity::from_raw(3);
+        let pos = IVec3::new(0, 0, 0);
+
+        let mut board = Board::with_seed(vec![agent], 0);
+        board.cache.insert_on_field(card);
+        board.cache.insert_on_slot(pos, card);
+        board.cache.insert_on_field(other);
+        board.cache.insert_by_agent(agent, card);
+
+        let entities = BoardQuery::query(
+            &board,
+            &[BoardQueryLoc::Field(None), BoardQueryLoc::OnSlot(pos)],
+        );
+        assert_eq!(entities.iter().filter(|e| **e == card).count(), 1);
+        assert!(entities.contains(&other));
+        assert_eq!(entities.len(), 2);
+
+        let owned = BoardQuery::query(&board, &[BoardQueryLoc::Field(Some(AgentOwned(agent)))]);
+        assert_eq!(owned.as_slice(), &[card]);
+    }
+
+    #[test]
+    fn locations_keep_their_order() {
+        let agent = Entity::from_raw(1);
+        let first = Entity::from_raw(2);
+        let second = Entity::from_raw(3);
+
+        let mut board = Board::with_seed(vec![agent], 0);
+        board.cache.insert_on_graveyard(agent, second);
+        board.cache.insert_on_graveyard(agent, first);
+        board.cache.insert_on_exile(agent, second);
+
+        let entities = BoardQuery::query(
+            &board,
+            &[
+                BoardQueryLoc::Graveyard(AgentOwned(agent)),
+                BoardQueryLoc::Exile(AgentOwned(agent)),
+            ],
+        );
+        assert_eq!(entities.as_slice(), &[second, first]);
     }
 }
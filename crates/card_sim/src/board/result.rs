@@ -0,0 +1,173 @@
+use bevy::{ecs::entity::MapEntities, prelude::*};
+use bevy_replicon::prelude::{SendMode, ToClients};
+use serde::{Deserialize, Serialize};
+
+use crate::{trigger_stage_transitions, Board, BoardGameState, BoardState, MatchResultPacket};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchResult {
+    Win { winner: Entity },
+    Draw,
+}
+
+impl MapEntities for MatchResult {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        if let MatchResult::Win { winner } = self {
+            *winner = entity_mapper.map_entity(*winner);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DefeatReason {
+    LifeZero,
+    DeckOut,
+    Concede,
+    Disconnect,
+    /// Defeats coming from custom [`WinCondition`]s or card effects
+    Other,
+}
+
+/// Triggered on the server when a board match ends, the result is also broadcasted to the clients with [`MatchResultPacket`]
+#[derive(Event, Clone, Copy, Debug)]
+pub struct MatchFinished {
+    pub board: Entity,
+    pub result: MatchResult,
+}
+
+/// A rule checked every frame on the running boards, returns the agents that lost and why
+/// Event like defeats (concede, deck out) don't need a condition and directly call [`BoardState::defeat_agent`]
+pub trait WinCondition: Send + Sync + 'static {
+    fn check(
+        &self,
+        world: &World,
+        board_entity: Entity,
+        board: &Board,
+    ) -> Vec<(Entity, DefeatReason)>;
+}
+
+#[derive(Resource, Default)]
+pub struct WinConditions(Vec<Box<dyn WinCondition>>);
+
+impl WinConditions {
+    pub fn add<C: WinCondition>(&mut self, condition: C) {
+        self.0.push(Box::new(condition));
+    }
+}
+
+pub trait WinConditionAppExt {
+    fn add_win_condition<C: WinCondition>(&mut self, condition: C) -> &mut Self;
+}
+
+impl WinConditionAppExt for App {
+    fn add_win_condition<C: WinCondition>(&mut self, condition: C) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(WinConditions::default)
+            .add(condition);
+        self
+    }
+}
+
+impl BoardState {
+    /// Mark the agent as defeated, the match result is resolved at the end of the frame so simultaneous defeats end in a draw
+    pub fn defeat_agent(&mut self, agent: Entity, reason: DefeatReason) {
+        if self.is_finished() || !self.agents.contains(&agent) {
+            return;
+        }
+        self.pending_defeats.push((agent, reason));
+    }
+
+    pub fn is_defeated(&self, agent: Entity) -> bool {
+        self.defeated.contains(&agent)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.game_state, BoardGameState::Finished { .. })
+    }
+
+    /// Apply the pending defeats and return the result if there is at most one agent left standing
+    pub(crate) fn resolve_defeats(&mut self) -> Option<MatchResult> {
+        if self.pending_defeats.is_empty() {
+            return None;
+        }
+
+        for (agent, reason) in std::mem::take(&mut self.pending_defeats) {
+            if !self.defeated.contains(&agent) {
+                info!("Agent {:?} got defeated ({:?})", agent, reason);
+                self.defeated.push(agent);
+            }
+        }
+
+        let mut remaining = self
+            .agents
+            .iter()
+            .filter(|agent| !self.defeated.contains(agent));
+
+        match (remaining.next(), remaining.next()) {
+            (None, _) => Some(MatchResult::Draw),
+            (Some(winner), None) => Some(MatchResult::Win { winner: *winner }),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn match_result_system(world: &mut World) {
+    let boards: Vec<Entity> = world
+        .query_filtered::<Entity, With<Board>>()
+        .iter(world)
+        .collect();
+
+    world.resource_scope(|world, conditions: Mut<WinConditions>| {
+        for board_entity in boards {
+            let defeats: Vec<(Entity, DefeatReason)> = match world.get::<Board>(board_entity) {
+                Some(board) if !board.state.is_finished() => conditions
+                    .0
+                    .iter()
+                    .flat_map(|condition| condition.check(world, board_entity, board))
+                    .collect(),
+                _ => continue,
+            };
+
+            let mut board = world.get_mut::<Board>(board_entity).unwrap();
+
+            for (agent, reason) in defeats {
+                board.state.defeat_agent(agent, reason);
+            }
+
+            let Some(result) = board.state.resolve_defeats() else {
+                // The match continues without the defeated agents, they can't keep the turn
+                if board
+                    .state
+                    .current_turn_agent
+                    .map_or(false, |agent| board.state.is_defeated(agent))
+                {
+                    match board.state.advance_turn() {
+                        Ok(transitions) => trigger_stage_transitions(
+                            &mut world.commands(),
+                            board_entity,
+                            transitions,
+                        ),
+                        Err(e) => error!(
+                            "Could not pass the turn of the defeated agent on the board {:?}: {}",
+                            board_entity, e
+                        ),
+                    }
+                }
+                continue;
+            };
+
+            info!("Match on board {:?} finished: {:?}", board_entity, result);
+            board.state.game_state = BoardGameState::Finished { result };
+            board.state.current_tree = None;
+
+            world.send_event(ToClients {
+                mode: SendMode::Broadcast,
+                event: MatchResultPacket::new(board_entity, result),
+            });
+            world.trigger(MatchFinished {
+                board: board_entity,
+                result,
+            });
+        }
+    });
+}
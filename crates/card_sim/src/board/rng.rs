@@ -0,0 +1,52 @@
+use rand::{Error, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// The random source of a board, every random outcome of the simulation must draw from it so a match can be replayed from its seed
+/// The seed is never replicated as it would let clients predict hidden informations like the deck order
+#[derive(Debug, Clone)]
+pub struct BoardRng {
+    seed: u64,
+    rng: ChaCha8Rng,
+}
+
+impl BoardRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn from_entropy() -> Self {
+        Self::from_seed(rand::random())
+    }
+
+    /// The seed the board was created with, the rng state itself is not reset by reading it
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Default for BoardRng {
+    fn default() -> Self {
+        Self::from_seed(0)
+    }
+}
+
+impl RngCore for BoardRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
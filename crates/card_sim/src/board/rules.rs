@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{BoardStage, TurnLimits, DEFAULT_STAGE_ORDER};
+
+/// The configurable rules of a board match, replicated so clients can display them
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone)]
+pub struct BoardRules {
+    pub starting_life: i32,
+
+    /// The stages of a turn in order, the first one starts the turn
+    pub stages: Vec<BoardStage>,
+
+    pub turn_limits: TurnLimits,
+
+    /// Seconds an agent has to answer when getting the priority on a chain before passing automatically
+    pub priority_timeout: f32,
+}
+
+impl Default for BoardRules {
+    fn default() -> Self {
+        Self {
+            starting_life: 20,
+            stages: DEFAULT_STAGE_ORDER.to_vec(),
+            turn_limits: TurnLimits::default(),
+            priority_timeout: 30.0,
+        }
+    }
+}
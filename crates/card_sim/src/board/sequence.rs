@@ -12,6 +12,15 @@ pub struct BoardSequence {
     pub runner: Box<dyn BoardActionRunner + 'static + Send + Sync>,
 }
 
+impl BoardSequence {
+    pub fn new<R: BoardActionRunner + 'static + Send + Sync>(seconds: f32, runner: R) -> Self {
+        Self {
+            channel_timer: Timer::from_seconds(seconds, TimerMode::Once),
+            runner: Box::new(runner),
+        }
+    }
+}
+
 impl Debug for BoardSequence {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("BoardAction")
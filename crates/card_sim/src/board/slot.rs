@@ -3,16 +3,26 @@ use bevy::{
     ecs::{
         component::{ComponentHooks, StorageType},
         entity::MapEntities,
+        world::Command,
     },
     utils::HashMap,
 };
 use epithet::utils::LevelEntity;
 use serde::{Deserialize, Serialize};
 
-use crate::{Board, OnBoard, OnField};
+use crate::{Board, CardVisibility, OnBoard, OnExile, OnField, OnGraveyard, OnHand};
 
 use super::BoardCache;
 
+/// Triggered on the server when an agent summoned a card from its hand to a slot
+#[derive(Event, Clone, Debug)]
+pub struct CardSummoned {
+    pub board: Entity,
+    pub card: Entity,
+    pub agent: Entity,
+    pub slot: Entity,
+}
+
 #[derive(Bundle)]
 pub struct CardSlotBundle {
     pub card_slot: BoardSlot,
@@ -80,10 +90,14 @@ impl Component for OnSlot {
 
     fn register_component_hooks(hooks: &mut ComponentHooks) {
         hooks.on_insert(|mut world, entity, _component_id| {
-            if let (Some(on_board), Some(on_slot), Some(slot)) = (
+            let Some(on_slot) = world.get::<OnSlot>(entity).cloned() else {
+                return;
+            };
+
+            // The BoardSlot is on the slot entity the OnSlot point to, not on the entity itself
+            if let (Some(on_board), Some(slot)) = (
                 world.get::<OnBoard>(entity).cloned(), // TODO get mut when there will no need to clone anymore with future bevy update
-                world.get::<OnSlot>(entity).cloned(),
-                world.get::<BoardSlot>(entity).cloned(),
+                world.get::<BoardSlot>(on_slot.0).cloned(),
             ) {
                 if let Some(mut board) = world.get_mut::<Board>(on_board.0) {
                     board.cache.insert_on_slot(slot.0, entity);
@@ -91,25 +105,33 @@ impl Component for OnSlot {
 
                 // Check if there is already an entity in the slot which should not be possible except if there was no verification before inserting it
                 // Will go in a invalid place state and will get cleanup and returned to the hand
-                if let Some(old_entity) = slot.1 {
+                if let Some(old_entity) = slot.1.filter(|old_entity| *old_entity != entity) {
                     error!("OnSlot component inserted pointing to a slot entity that already has an entity in it (old entity: {:?}, new_entity {:?}), this is a code error that will cause a invalid place error, check the verification before inserting OnSlot", old_entity, entity);
                     if let Some(mut old_entity_commands) = world.commands().get_entity(old_entity) {
                         old_entity_commands.remove::<OnSlot>();
                     }
                 }
-                world.get_mut::<BoardSlot>(entity).unwrap().1 = Some(entity); //TODO modify in future bevy update so there is no need to get it again
+                world.get_mut::<BoardSlot>(on_slot.0).unwrap().1 = Some(entity); //TODO modify in future bevy update so there is no need to get it again
             }
         });
 
         hooks.on_remove(|mut world, entity, _component_id| {
-            if let (Some(on_board), Some(on_slot), Some(slot)) = (
+            let Some(on_slot) = world.get::<OnSlot>(entity).cloned() else {
+                return;
+            };
+
+            if let (Some(on_board), Some(slot)) = (
                 world.get::<OnBoard>(entity).cloned(),
-                world.get::<OnSlot>(entity).cloned(),
-                world.get::<BoardSlot>(entity).cloned(),
+                world.get::<BoardSlot>(on_slot.0).cloned(),
             ) {
+                // The slot may already hold a new entity if this one got replaced, only clean what still point to this entity
                 if let Some(mut board) = world.get_mut::<Board>(on_board.0) {
-                    board.cache.remove_from_slot(&slot.0);
-                    world.get_mut::<BoardSlot>(entity).unwrap().1 = None; //TODO modify in future bevy update so there is no need to get it again
+                    if board.cache.get_slot_occupant(&slot.0) == Some(&entity) {
+                        board.cache.remove_from_slot(&slot.0);
+                    }
+                }
+                if slot.1 == Some(entity) {
+                    world.get_mut::<BoardSlot>(on_slot.0).unwrap().1 = None; //TODO modify in future bevy update so there is no need to get it again
                 }
             }
         });
@@ -129,21 +151,110 @@ impl BoardCache {
 
     pub fn insert_on_slot(&mut self, pos: IVec3, entity: Entity) {
         self.on_slot_lookup.insert(pos, entity);
+        self.stamp_field(entity);
     }
 
     pub(crate) fn remove_from_slot(&mut self, pos: &IVec3) -> Option<Entity> {
-        self.on_slot_lookup.remove(pos)
+        let removed = self.on_slot_lookup.remove(pos);
+        if let Some(entity) = removed {
+            self.unstamp_field(entity);
+        }
+        removed
     }
 
     pub(crate) fn remove_slot(&mut self, pos: &IVec3) -> Option<Entity> {
         self.slots_lookup.remove(pos)
     }
 
+    /// The slot entity at the position, see [`BoardCache::get_slot_occupant`] for the entity placed in it
     pub fn get_on_slot(&self, pos: &IVec3) -> Option<&Entity> {
         self.slots_lookup.get(pos)
     }
 
+    /// The entity placed in the slot at the position
+    pub fn get_slot_occupant(&self, pos: &IVec3) -> Option<&Entity> {
+        self.on_slot_lookup.get(pos)
+    }
+
+    pub fn get_entities_on_slots(&self) -> impl Iterator<Item = &Entity> {
+        self.on_slot_lookup.values()
+    }
+
     pub fn get_slots(&self) -> &HashMap<IVec3, Entity> {
         &self.slots_lookup
     }
 }
+
+/// Move a card of the board to an empty slot, server side only
+pub struct MoveToSlotCommand {
+    pub board: Entity,
+    pub entity: Entity,
+    pub slot: IVec3,
+}
+
+impl Command for MoveToSlotCommand {
+    fn apply(self, world: &mut World) {
+        let Some(board) = world.get::<Board>(self.board) else {
+            warn!(
+                "MoveToSlotCommand: board {:?} does not exist, skipping",
+                self.board
+            );
+            return;
+        };
+
+        let Some(slot_entity) = board.cache.get_on_slot(&self.slot).copied() else {
+            warn!(
+                "MoveToSlotCommand: there is no slot at {:?} on the board {:?}, skipping",
+                self.slot, self.board
+            );
+            return;
+        };
+
+        if board.cache.get_slot_occupant(&self.slot).is_some() {
+            warn!(
+                "MoveToSlotCommand: the slot {:?} is already taken, skipping",
+                self.slot
+            );
+            return;
+        }
+
+        let Some(mut entity) = world.get_entity_mut(self.entity) else {
+            warn!(
+                "MoveToSlotCommand: entity {:?} does not exist, skipping",
+                self.entity
+            );
+            return;
+        };
+
+        let previous_slot = entity
+            .get::<OnSlot>()
+            .map(|on_slot| on_slot.0)
+            .filter(|previous| *previous != slot_entity);
+
+        entity.remove::<(OnHand, OnGraveyard, OnExile)>();
+
+        if let Some(mut visibility) = entity.get_mut::<CardVisibility>() {
+            visibility.visible_to_all = true;
+        }
+
+        // The new location is inserted before the previous one is removed so a card coming from the field or another slot keep its field timestamp
+        entity.insert(OnSlot(slot_entity));
+        entity.remove::<OnField>();
+
+        // Replacing the OnSlot doesn't run its remove hook, the previous slot is freed here
+        if let Some(previous_slot) = previous_slot {
+            let pos = world.get::<BoardSlot>(previous_slot).map(|slot| slot.0);
+
+            if let Some(mut slot) = world.get_mut::<BoardSlot>(previous_slot) {
+                if slot.1 == Some(self.entity) {
+                    slot.1 = None;
+                }
+            }
+            if let (Some(pos), Some(mut board)) = (pos, world.get_mut::<Board>(self.board)) {
+                if board.cache.get_slot_occupant(&pos) == Some(&self.entity) {
+                    board.cache.remove_from_slot(&pos);
+                }
+            }
+        }
+    }
+}
@@ -1,42 +1,241 @@
+use std::fmt::{self, Display, Formatter};
+
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::TurnCounters;
+
 use super::BoardState;
 
 #[derive(Reflect, Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Default)]
 pub enum BoardStage {
     #[default]
     Start,
+    Draw,
+    Standby,
     Main,
+    Battle,
     End,
 }
 
-/// Advances the board state to the specified stage.
-///
-/// # Arguments
-///
-/// * `stage` - The target stage to advance to.
-///
-/// # Returns
-///
-/// * `bool` - Always returns `true`.
+/// The default turn structure, boards can use another order with [`crate::BoardRules::stages`]
+pub const DEFAULT_STAGE_ORDER: &[BoardStage] = &[
+    BoardStage::Start,
+    BoardStage::Draw,
+    BoardStage::Standby,
+    BoardStage::Main,
+    BoardStage::Battle,
+    BoardStage::End,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StageChangeError {
+    NoAgents,
+    /// The rules of the board have an empty stage order
+    NoStages,
+    /// The stage is not part of the stage order of the board
+    UnknownStage(BoardStage),
+    /// Stages can only move forward, going back to the first stage starts the next turn
+    Backward {
+        from: BoardStage,
+        to: BoardStage,
+    },
+}
+
+impl Display for StageChangeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StageChangeError::NoAgents => write!(f, "the board has no agents"),
+            StageChangeError::NoStages => write!(f, "the board rules have no stages"),
+            StageChangeError::UnknownStage(stage) => {
+                write!(f, "the stage {:?} is not used by this board", stage)
+            }
+            StageChangeError::Backward { from, to } => {
+                write!(f, "can't go back from the stage {:?} to {:?}", from, to)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StageChangeError {}
+
+/// A single step of a stage change, in the order they happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StageTransition {
+    Exited {
+        agent: Entity,
+        stage: BoardStage,
+        turn: u32,
+    },
+    Entered {
+        agent: Entity,
+        stage: BoardStage,
+        turn: u32,
+    },
+}
+
+/// Triggered on the server for every stage left during a stage change, the agent is the turn agent of the stage
+#[derive(Event, Clone, Debug)]
+pub struct StageExited {
+    pub board: Entity,
+    pub agent: Entity,
+    pub stage: BoardStage,
+    pub turn: u32,
+}
+
+/// Triggered on the server for every stage entered during a stage change, the agent is the turn agent of the stage
+#[derive(Event, Clone, Debug)]
+pub struct StageEntered {
+    pub board: Entity,
+    pub agent: Entity,
+    pub stage: BoardStage,
+    pub turn: u32,
+}
+
 impl BoardState {
-    pub fn advance_stage(&mut self, stage: BoardStage) -> bool {
+    pub fn get_stage(&self) -> &BoardStage {
+        &self.stage
+    }
+
+    /// The number of the current turn, starting at 1 when the game starts and 0 before
+    pub fn get_turn(&self) -> u32 {
+        self.turn
+    }
+
+    /// Is the stage the one starting every turn
+    pub fn is_turn_start(&self, stage: &BoardStage) -> bool {
+        self.rules.stages.first() == Some(stage)
+    }
+
+    /// Check that the stage can be reached from the current one without changing anything
+    pub fn can_advance_stage(&self, stage: &BoardStage) -> Result<(), StageChangeError> {
         if self.agents.is_empty() {
-            error!("Cannot advance stage on a board with no agents, this function should never be called in this state");
-            return false;
+            return Err(StageChangeError::NoAgents);
         }
 
-        //TODO make chain stage when the target stage result in multiple stage change to trigger effects on each stage
-        if stage == BoardStage::Start {
+        let stages = &self.rules.stages;
+        let Some(target_index) = stages.iter().position(|s| s == stage) else {
+            return Err(if stages.is_empty() {
+                StageChangeError::NoStages
+            } else {
+                StageChangeError::UnknownStage(stage.clone())
+            });
+        };
+
+        // Going back to the first stage is always allowed as it passes the turn
+        if target_index != 0 && target_index <= self.stage_index() {
+            return Err(StageChangeError::Backward {
+                from: self.stage.clone(),
+                to: stage.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Advances the board state to the specified stage, going through every stage in between
+    /// Targeting the first stage of the order ends the turn and starts the turn of the next agent
+    /// Returns the stages exited and entered in order so the caller can fire their events with [`trigger_stage_transitions`]
+    pub fn advance_stage(
+        &mut self,
+        stage: BoardStage,
+    ) -> Result<Vec<StageTransition>, StageChangeError> {
+        self.can_advance_stage(&stage)?;
+
+        let mut transitions = Vec::new();
+
+        loop {
+            let agent = self.current_turn_agent.unwrap_or(self.agents[0]);
+            transitions.push(StageTransition::Exited {
+                agent,
+                stage: self.stage.clone(),
+                turn: self.turn,
+            });
+
+            let next_index = self.stage_index() + 1;
+
+            if next_index < self.rules.stages.len() {
+                self.stage = self.rules.stages[next_index].clone();
+            } else {
+                self.stage = self.rules.stages[0].clone();
+                self.next_turn_agent();
+            }
+
+            transitions.push(StageTransition::Entered {
+                agent: self.current_turn_agent.unwrap_or(agent),
+                stage: self.stage.clone(),
+                turn: self.turn,
+            });
+
+            if self.stage == stage {
+                return Ok(transitions);
+            }
+        }
+    }
+
+    /// End the current turn, going through the remaining stages
+    pub fn advance_turn(&mut self) -> Result<Vec<StageTransition>, StageChangeError> {
+        let first = self
+            .rules
+            .stages
+            .first()
+            .cloned()
+            .ok_or(StageChangeError::NoStages)?;
+        self.advance_stage(first)
+    }
+
+    /// A stage missing from the order (ex: the rules changed) is considered as the last one so the next change starts a new turn
+    pub(crate) fn stage_index(&self) -> usize {
+        self.rules
+            .stages
+            .iter()
+            .position(|s| *s == self.stage)
+            .unwrap_or(self.rules.stages.len().saturating_sub(1))
+    }
+
+    fn next_turn_agent(&mut self) {
+        // Defeated agents are kept in the turn order but skip their turns
+        for _ in 0..self.agents.len() {
             self.current_turn_agent_index = if self.current_turn_agent_index < self.agents.len() - 1
             {
                 self.current_turn_agent_index + 1
             } else {
                 0
             };
-            self.current_turn_agent = Some(self.agents[self.current_turn_agent_index]);
+            if !self.is_defeated(self.agents[self.current_turn_agent_index]) {
+                break;
+            }
+        }
+        self.current_turn_agent = Some(self.agents[self.current_turn_agent_index]);
+        self.turn += 1;
+        self.turn_counters = TurnCounters::default();
+        self.name_activations.end_turn();
+    }
+}
+
+/// Fire the [`StageExited`] and [`StageEntered`] events of a stage change in the order they happened
+pub fn trigger_stage_transitions(
+    commands: &mut Commands,
+    board: Entity,
+    transitions: Vec<StageTransition>,
+) {
+    for transition in transitions {
+        match transition {
+            StageTransition::Exited { agent, stage, turn } => {
+                commands.trigger(StageExited {
+                    board,
+                    agent,
+                    stage,
+                    turn,
+                });
+            }
+            StageTransition::Entered { agent, stage, turn } => {
+                commands.trigger(StageEntered {
+                    board,
+                    agent,
+                    stage,
+                    turn,
+                });
+            }
         }
-        true
     }
 }
@@ -2,7 +2,10 @@ use bevy::ecs::entity::MapEntities;
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::{Board, BoardStage, Tree};
+use crate::{
+    Board, BoardRng, BoardRules, BoardStage, DefeatReason, MatchResult, NameActivations,
+    PendingTargets, Tree, TurnCounters,
+};
 
 use super::{BoardActionRunner, BoardSequence};
 
@@ -17,6 +20,12 @@ pub struct BoardState {
 
     pub(crate) stage: BoardStage,
 
+    pub(crate) turn: u32,
+
+    pub(crate) turn_counters: TurnCounters,
+
+    pub rules: BoardRules,
+
     #[serde(skip)]
     #[reflect(ignore)]
     pub game_state: BoardGameState,
@@ -24,43 +33,73 @@ pub struct BoardState {
     #[serde(skip)]
     pub tick_triggers: Vec<(Entity, usize)>,
 
+    /// Agents that lost but are kept in the agents list so the turn order and results stay coherent
+    #[serde(skip)]
+    pub(crate) defeated: Vec<Entity>,
+
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub(crate) pending_defeats: Vec<(Entity, DefeatReason)>,
+
     #[serde(skip)]
     #[reflect(ignore)]
     pub(crate) current_tree: Option<Tree>,
+
+    /// The activations counted by the name scoped activation limits, server side only
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub(crate) name_activations: NameActivations,
+
+    /// Server side only, clients get a default rng as the seed must stay hidden
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub(crate) rng: BoardRng,
 }
 
 impl BoardState {
-    pub fn new(agents: Vec<Entity>) -> Self {
+    pub fn new(agents: Vec<Entity>, rng: BoardRng) -> Self {
         Self {
             current_turn_agent: None,
             current_turn_agent_index: 0,
             stage: BoardStage::Start,
+            turn: 0,
+            turn_counters: TurnCounters::default(),
+            rules: BoardRules::default(),
             current_tree: None,
             game_state: BoardGameState::Open,
             tick_triggers: Vec::new(),
+            defeated: Vec::new(),
+            pending_defeats: Vec::new(),
+            name_activations: NameActivations::default(),
+            rng,
             agents,
         }
     }
 
     pub fn game_start(&mut self) {
         self.current_turn_agent = Some(self.agents[0]);
+        self.stage = self.rules.stages.first().cloned().unwrap_or_default();
+        self.turn = 1;
+        self.turn_counters = TurnCounters::default();
     }
 
     pub fn trigger_effect(&mut self, card_entity: Entity, effect_index: usize) {
         self.tick_triggers.push((card_entity, effect_index));
     }
 
-    pub fn activate_effect(&mut self, card_entity: Entity) {
-        if let Some(ref mut tree) = self.current_tree {
-            tree.push_card(card_entity);
-        } else {
-            self.current_tree = Some(Tree::new(card_entity));
-        }
-    }
-
     pub fn get_current_turn_agent(&self) -> &Option<Entity> {
         &self.current_turn_agent
     }
+
+    /// The rng every random outcome of this board must draw from
+    pub fn rng(&mut self) -> &mut BoardRng {
+        &mut self.rng
+    }
+
+    /// The seed of the board rng, recorded so the match can be replayed with [`Board::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
+    }
 }
 
 impl MapEntities for BoardState {
@@ -82,20 +121,44 @@ pub enum BoardGameState {
     Open,
     Sequence(BoardSequence),
     Action,
+    /// The match ended, no actions are accepted anymore
+    Finished {
+        result: MatchResult,
+    },
 }
 
 pub(crate) fn board_state_update(
-    mut boards: Query<&mut Board>,
+    mut boards: Query<(Entity, &mut Board)>,
     time: Res<Time>,
+    pending_targets: Res<PendingTargets>,
     mut commands: Commands,
 ) {
-    for mut board in boards.iter_mut() {
+    for (board_entity, mut board) in boards.iter_mut() {
         if let BoardGameState::Sequence(action) = &mut board.state.game_state {
             action.channel_timer.tick(time.delta());
 
             if action.channel_timer.finished() {
                 action.runner.execute(&mut commands);
+                // A sequence only run once, the runner can start a new one through its commands
+                board.state.game_state = BoardGameState::Open;
+            }
+        }
+
+        // The timeout wait while the agent with the priority pays the costs of an activation, the costs selection has its own timeout
+        let expired = board.state.current_tree.as_mut().and_then(|tree| {
+            if pending_targets.has_pending_activation(board_entity, tree.get_priority()) {
+                return None;
+            }
+            let timeout = tree.priority_timeout.as_mut()?;
+            timeout.channel_timer.tick(time.delta());
+            if timeout.channel_timer.finished() {
+                tree.priority_timeout.take()
+            } else {
+                None
             }
+        });
+        if let Some(timeout) = expired {
+            timeout.runner.execute(&mut commands);
         }
     }
 }
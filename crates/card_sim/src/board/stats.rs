@@ -0,0 +1,197 @@
+use bevy::{ecs::world::Command, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{Board, DefeatReason, WinCondition};
+
+/// The game stats of an agent, stored on the agent entity and replicated to every clients
+/// Only the server should mutate it, through [`ModifyStatCommand`] so the changes are observable with [`AgentStatChanged`]
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AgentStats {
+    life: i32,
+
+    /// Generic resources like mana/energy, a missing counter is worth 0
+    counters: HashMap<String, i32>,
+}
+
+impl AgentStats {
+    pub fn new(life: i32) -> Self {
+        Self {
+            life,
+            counters: HashMap::new(),
+        }
+    }
+
+    pub fn life(&self) -> i32 {
+        self.life
+    }
+
+    pub fn counter(&self, name: &str) -> i32 {
+        self.counters.get(name).copied().unwrap_or(0)
+    }
+
+    pub fn get(&self, stat: &AgentStat) -> i32 {
+        match stat {
+            AgentStat::Life => self.life,
+            AgentStat::Counter(name) => self.counter(name),
+        }
+    }
+
+    fn set(&mut self, stat: &AgentStat, value: i32) {
+        match stat {
+            AgentStat::Life => self.life = value,
+            AgentStat::Counter(name) => {
+                self.counters.insert(name.clone(), value);
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AgentStat {
+    Life,
+    Counter(String),
+}
+
+/// Triggered on the server when a stat of an agent changed through a [`ModifyStatCommand`]
+#[derive(Event, Clone, Debug)]
+pub struct AgentStatChanged {
+    pub board: Entity,
+    pub agent: Entity,
+    pub stat: AgentStat,
+    pub old: i32,
+    pub new: i32,
+}
+
+/// Triggered on the server after [`AgentStatChanged`] when the life of an agent went down
+#[derive(Event, Clone, Debug)]
+pub struct DamageDealt {
+    pub board: Entity,
+    pub agent: Entity,
+    pub amount: i32,
+}
+
+#[derive(Clone, Debug)]
+pub enum StatModification {
+    Add(i32),
+    Set(i32),
+    /// Remove the amount as the cost of an effect, paying life is not a damage
+    Pay(i32),
+}
+
+/// Modify a stat of an agent playing on the board, server side only
+pub struct ModifyStatCommand {
+    pub board: Entity,
+    pub agent: Entity,
+    pub stat: AgentStat,
+    pub modification: StatModification,
+}
+
+impl ModifyStatCommand {
+    pub fn new(
+        board: Entity,
+        agent: Entity,
+        stat: AgentStat,
+        modification: StatModification,
+    ) -> Self {
+        Self {
+            board,
+            agent,
+            stat,
+            modification,
+        }
+    }
+
+    /// Damage are negative values, gains are positive
+    pub fn life(board: Entity, agent: Entity, amount: i32) -> Self {
+        Self::new(board, agent, AgentStat::Life, StatModification::Add(amount))
+    }
+
+    pub fn counter<S: Into<String>>(board: Entity, agent: Entity, name: S, amount: i32) -> Self {
+        Self::new(
+            board,
+            agent,
+            AgentStat::Counter(name.into()),
+            StatModification::Add(amount),
+        )
+    }
+}
+
+impl Command for ModifyStatCommand {
+    fn apply(self, world: &mut World) {
+        let playing = world
+            .get::<Board>(self.board)
+            .map_or(false, |board| board.state.agents.contains(&self.agent));
+
+        if !playing {
+            warn!(
+                "ModifyStatCommand: agent {:?} is not playing on the board {:?}, skipping",
+                self.agent, self.board
+            );
+            return;
+        }
+
+        let Some(mut stats) = world.get_mut::<AgentStats>(self.agent) else {
+            error!(
+                "ModifyStatCommand: agent {:?} has no stats, skipping",
+                self.agent
+            );
+            return;
+        };
+
+        let old = stats.get(&self.stat);
+        let new = match self.modification {
+            StatModification::Add(amount) => old.saturating_add(amount),
+            StatModification::Set(value) => value,
+            StatModification::Pay(amount) => old.saturating_sub(amount),
+        };
+        let paid = matches!(self.modification, StatModification::Pay(_));
+
+        if old == new {
+            return;
+        }
+
+        stats.set(&self.stat, new);
+        let damaged = self.stat == AgentStat::Life && new < old && !paid;
+
+        world.trigger(AgentStatChanged {
+            board: self.board,
+            agent: self.agent,
+            stat: self.stat,
+            old,
+            new,
+        });
+
+        if damaged {
+            world.trigger(DamageDealt {
+                board: self.board,
+                agent: self.agent,
+                amount: old - new,
+            });
+        }
+    }
+}
+
+/// Agents with no life left lose the match
+pub struct LifeZeroCondition;
+
+impl WinCondition for LifeZeroCondition {
+    fn check(
+        &self,
+        world: &World,
+        _board_entity: Entity,
+        board: &Board,
+    ) -> Vec<(Entity, DefeatReason)> {
+        board
+            .state
+            .agents
+            .iter()
+            .filter(|agent| !board.state.is_defeated(**agent))
+            .filter(|agent| {
+                world
+                    .get::<AgentStats>(**agent)
+                    .map_or(false, |stats| stats.life() <= 0)
+            })
+            .map(|agent| (*agent, DefeatReason::LifeZero))
+            .collect()
+    }
+}
@@ -1,34 +1,95 @@
-use bevy::prelude::Entity;
-use synctree::{Node, NodeArena};
+use bevy::{
+    ecs::entity::MapEntities,
+    prelude::{Entity, EntityMapper},
+};
+use serde::{Deserialize, Serialize};
 
+use super::BoardSequence;
+
+/// An effect activation waiting on the chain to resolve
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainLink {
+    pub card: Entity,
+    pub effect_index: usize,
+    /// The agent that activated the effect
+    pub agent: Entity,
+    /// The speed of the effect when it got activated, responses need at least the same speed
+    pub speed: i32,
+}
+
+impl MapEntities for ChainLink {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.card = entity_mapper.map_entity(self.card);
+        self.agent = entity_mapper.map_entity(self.agent);
+    }
+}
+
+/// The chain of effects currently being built on a board
+/// Links resolve last in first out once every agent passed its priority in a row
 #[derive(Debug)]
 pub struct Tree {
-    tree: NodeArena<Entity>,
-    current_leaf: Option<Node<Entity>>,
+    links: Vec<ChainLink>,
+
+    /// The agent that can respond to the last link
+    priority: Entity,
+
+    /// The agents that passed since the last link got added
+    passed: Vec<Entity>,
+
+    /// Pass the priority of the agent holding it when it runs out, dropped with the chain
+    pub(crate) priority_timeout: Option<BoardSequence>,
 }
 
 impl Tree {
-    pub fn new(card: Entity) -> Self {
-        let tree = NodeArena::new();
-        let current_leaf = Node::new(card, &tree);
-
+    pub fn new(link: ChainLink, priority: Entity) -> Self {
         Self {
-            tree,
-            current_leaf: Some(current_leaf),
+            links: vec![link],
+            priority,
+            passed: Vec::new(),
+            priority_timeout: None,
         }
     }
 
-    pub fn push_card(&mut self, card: Entity) {
-        if let Some(current_leaf) = &self.current_leaf {
-            current_leaf.append(&Node::new(card, &self.tree), &self.tree);
-        } else {
-            self.current_leaf = Some(Node::new(card, &self.tree));
-        }
+    /// Add a response to the chain, everyone get the chance to respond to it again
+    pub fn push_link(&mut self, link: ChainLink, priority: Entity) {
+        self.links.push(link);
+        self.priority = priority;
+        self.passed.clear();
+    }
+
+    pub fn pop_link(&mut self) -> Option<ChainLink> {
+        self.links.pop()
     }
 
-    pub fn pop_card(&mut self) {
-        if let Some(current_leaf) = &self.current_leaf {
-            self.current_leaf = current_leaf.parent(&self.tree);
+    pub fn last_link(&self) -> Option<&ChainLink> {
+        self.links.last()
+    }
+
+    /// The links in activation order
+    pub fn links(&self) -> &[ChainLink] {
+        &self.links
+    }
+
+    pub fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    pub fn get_priority(&self) -> Entity {
+        self.priority
+    }
+
+    pub(crate) fn pass(&mut self, agent: Entity, next: Entity) {
+        if !self.passed.contains(&agent) {
+            self.passed.push(agent);
         }
+        self.priority = next;
+    }
+
+    pub fn has_passed(&self, agent: Entity) -> bool {
+        self.passed.contains(&agent)
     }
 }
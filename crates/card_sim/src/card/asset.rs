@@ -0,0 +1,177 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadedFolder},
+    prelude::*,
+    utils::HashMap,
+};
+
+use crate::{Board, EffectTriggerKind, Effects, OnBoard, TagRegistry};
+
+use super::{CardAttribute, CardData, CardDataError, CardId, CardRegistry};
+
+/// Loads card data files as [`CardData`] assets
+/// Only the `.card.json` and `.card.ron` extensions are claimed so other json/ron assets keep their own loaders
+#[derive(Default)]
+pub struct CardDataLoader;
+
+impl AssetLoader for CardDataLoader {
+    type Asset = CardData;
+    type Settings = ();
+    type Error = CardDataError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(|error| CardDataError::Io {
+                path: load_context.path().to_path_buf(),
+                error,
+            })?;
+
+        CardData::from_bytes(load_context.path(), &bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["card.json", "card.ron"]
+    }
+}
+
+/// Keep the card data folder alive and remember which asset registered which card so the registry can follow the files changes
+#[derive(Resource)]
+pub struct CardDataAssets {
+    pub folder: Handle<LoadedFolder>,
+    loaded: HashMap<AssetId<CardData>, CardId>,
+}
+
+impl CardDataAssets {
+    pub fn new(folder: Handle<LoadedFolder>) -> Self {
+        Self {
+            folder,
+            loaded: HashMap::new(),
+        }
+    }
+}
+
+/// Sent when a card definition got added, modified or removed from the [`CardRegistry`] by the card data assets
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CardDataChanged(pub CardId);
+
+pub(crate) fn card_data_asset_event_system(
+    mut asset_events: EventReader<AssetEvent<CardData>>,
+    mut changed_writer: EventWriter<CardDataChanged>,
+    assets: Res<Assets<CardData>>,
+    asset_server: Res<AssetServer>,
+    tags: Res<TagRegistry>,
+    mut card_assets: ResMut<CardDataAssets>,
+    mut registry: ResMut<CardRegistry>,
+) {
+    for event in asset_events.read() {
+        match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => {
+                let Some(data) = assets.get(*id) else {
+                    continue;
+                };
+
+                // The loader has no access to the world, the tags are checked once the asset is loaded
+                let path = asset_server
+                    .get_path(*id)
+                    .map(|path| path.path().to_path_buf())
+                    .unwrap_or_default();
+                if let Err(error) = data.validate_tags(&path, &tags) {
+                    error!("Failed to load card data: {}", error);
+                    continue;
+                }
+
+                if let Some((_, other_id)) = card_assets
+                    .loaded
+                    .iter()
+                    .find(|(asset_id, card_id)| *asset_id != id && **card_id == data.id)
+                {
+                    error!(
+                        "Card data '{}' use the id {} which is already used by another card data file, skipping",
+                        data.name, other_id.0
+                    );
+                    continue;
+                }
+
+                // The id of the card itself can be edited, the old definition must not stay in the registry
+                if let Some(old_id) = card_assets.loaded.insert(*id, data.id) {
+                    if old_id != data.id {
+                        registry.remove(&old_id);
+                        changed_writer.send(CardDataChanged(old_id));
+                    }
+                }
+                registry.insert(data.clone());
+                changed_writer.send(CardDataChanged(data.id));
+            }
+            AssetEvent::Removed { id } => {
+                if let Some(card_id) = card_assets.loaded.remove(id) {
+                    registry.remove(&card_id);
+                    changed_writer.send(CardDataChanged(card_id));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Refresh the name, effects and effect triggers of the card instances whose definition changed
+/// The effects are recreated, their cooldowns are carried over by effect index
+pub(crate) fn refresh_card_instances_system(
+    mut changed_reader: EventReader<CardDataChanged>,
+    registry: Res<CardRegistry>,
+    mut cards: Query<(
+        Entity,
+        &CardAttribute,
+        &mut Name,
+        &mut Effects,
+        Option<&OnBoard>,
+    )>,
+    mut boards: Query<&mut Board>,
+    mut commands: Commands,
+) {
+    for CardDataChanged(card_id) in changed_reader.read() {
+        let Some(data) = registry.get(card_id) else {
+            warn!(
+                "Card definition {} was removed, its existing instances keep their old data",
+                card_id.0
+            );
+            continue;
+        };
+
+        for (entity, attribute, mut name, mut effects, on_board) in cards.iter_mut() {
+            if attribute.id == *card_id {
+                *name = Name::new(data.name.clone());
+                let mut reloaded = data.create_effects();
+                for (effect, previous) in reloaded.iter_mut().zip(effects.iter()) {
+                    effect
+                        .get_cooldown_mut()
+                        .carry_over(previous.get_cooldown());
+                }
+                *effects = reloaded;
+
+                // The effects may have moved or changed their trigger, the old triggers would point to the wrong indexes
+                let data = data.clone();
+                commands.add(move |world: &mut World| {
+                    if let Some(mut card) = world.get_entity_mut(entity) {
+                        EffectTriggerKind::remove_triggers(&mut card);
+                        data.insert_effect_triggers(&mut card);
+                    }
+                });
+
+                // The continuous effects of the card may have changed too
+                if let Some(mut board) =
+                    on_board.and_then(|on_board| boards.get_mut(on_board.0).ok())
+                {
+                    board.cache.continuous_dirty = true;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+use bevy::utils::HashSet;
+
+use crate::{EffectCostKind, TagRegistry, NORMAL_EFFECT_SPEED};
+
+use super::{CardData, CardId, CardRegistry};
+
+/// Extensions of the files that can be read as card data
+pub const CARD_DATA_EXTENSIONS: &[&str] = &["json", "ron"];
+
+#[derive(Debug)]
+pub enum CardDataError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    /// The file is not valid json/ron or does not match the card data shape, the message contains the line and column
+    Parse {
+        path: PathBuf,
+        message: String,
+    },
+    /// The file was parsed but one of its field hold a value that can't be used
+    Invalid {
+        path: PathBuf,
+        field: String,
+        reason: String,
+    },
+    UnsupportedFormat {
+        path: PathBuf,
+    },
+}
+
+impl Display for CardDataError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CardDataError::Io { path, error } => {
+                write!(f, "{}: could not read the file: {}", path.display(), error)
+            }
+            CardDataError::Parse { path, message } => write!(f, "{}: {}", path.display(), message),
+            CardDataError::Invalid {
+                path,
+                field,
+                reason,
+            } => write!(f, "{}: field `{}`: {}", path.display(), field, reason),
+            CardDataError::UnsupportedFormat { path } => write!(
+                f,
+                "{}: unsupported card data format, expected one of {:?}",
+                path.display(),
+                CARD_DATA_EXTENSIONS
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CardDataError {}
+
+impl CardData {
+    /// Parse a card definition, the format is chosen from the path extension
+    /// The path is only used for the format and the error reporting, nothing is read from the disk
+    pub fn from_bytes(path: &Path, bytes: &[u8]) -> Result<Self, CardDataError> {
+        let data: CardData = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::from_slice(bytes).map_err(|e| CardDataError::Parse {
+                path: path.to_path_buf(),
+                message: e.to_string(),
+            })?,
+            Some("ron") => ron::de::from_bytes(bytes).map_err(|e| CardDataError::Parse {
+                path: path.to_path_buf(),
+                message: e.to_string(),
+            })?,
+            _ => {
+                return Err(CardDataError::UnsupportedFormat {
+                    path: path.to_path_buf(),
+                })
+            }
+        };
+
+        data.validate(path)?;
+        Ok(data)
+    }
+
+    /// Check the values serde can't check by itself
+    pub fn validate(&self, path: &Path) -> Result<(), CardDataError> {
+        let invalid = |field: String, reason: &str| CardDataError::Invalid {
+            path: path.to_path_buf(),
+            field,
+            reason: reason.to_string(),
+        };
+
+        if self.name.trim().is_empty() {
+            return Err(invalid("name".to_string(), "the card name can't be empty"));
+        }
+
+        for (effect_index, effect) in self.effects.iter().enumerate() {
+            if effect.speed < NORMAL_EFFECT_SPEED {
+                return Err(invalid(
+                    format!("effects[{}].speed", effect_index),
+                    "an effect speed can't be lower than the normal speed",
+                ));
+            }
+
+            if let Some(limit) = effect.cooldown.as_ref().and_then(|cooldown| cooldown.limit) {
+                if limit.count == 0 {
+                    return Err(invalid(
+                        format!("effects[{}].cooldown.limit.count", effect_index),
+                        "an activation limit must allow at least 1 activation",
+                    ));
+                }
+            }
+
+            for (cost_index, cost) in effect.costs.iter().enumerate() {
+                let paid = match cost {
+                    EffectCostKind::Discard { count } | EffectCostKind::Tribute { count } => {
+                        *count > 0
+                    }
+                    EffectCostKind::PayLife { amount }
+                    | EffectCostKind::RemoveCounters { amount, .. } => *amount > 0,
+                };
+                if !paid {
+                    return Err(invalid(
+                        format!("effects[{}].costs[{}]", effect_index, cost_index),
+                        "a cost must be greater than 0",
+                    ));
+                }
+            }
+
+            let mut group_names = HashSet::new();
+
+            for (group_index, group) in effect.targets_groups.iter().enumerate() {
+                let field = format!("effects[{}].targets_groups[{}]", effect_index, group_index);
+
+                if group.name.trim().is_empty() {
+                    return Err(invalid(
+                        format!("{}.name", field),
+                        "a target group name can't be empty",
+                    ));
+                }
+                if !group_names.insert(group.name.as_str()) {
+                    return Err(invalid(
+                        format!("{}.name", field),
+                        "a target group with the same name already exist in this effect",
+                    ));
+                }
+                if group.zones.is_empty() {
+                    return Err(invalid(
+                        format!("{}.zones", field),
+                        "a target group need at least one zone",
+                    ));
+                }
+                if group.max == 0 || group.min > group.max {
+                    return Err(invalid(
+                        format!("{}.max", field),
+                        "the max count of a target group must be at least 1 and not lower than the min count",
+                    ));
+                }
+            }
+
+            for (action_index, action) in effect.actions.iter().enumerate() {
+                if let Some(targets_group) = action.targets_group() {
+                    if !group_names.contains(targets_group) {
+                        return Err(invalid(
+                            format!(
+                                "effects[{}].actions[{}].targets_group",
+                                effect_index, action_index
+                            ),
+                            "the action use a target group that does not exist in this effect",
+                        ));
+                    }
+                }
+            }
+
+            for (modifier_index, modifier) in effect.continuous.iter().enumerate() {
+                let field = format!("effects[{}].continuous[{}]", effect_index, modifier_index);
+
+                if modifier.stat.trim().is_empty() {
+                    return Err(invalid(
+                        format!("{}.stat", field),
+                        "a continuous modifier need a stat",
+                    ));
+                }
+                if !group_names.contains(modifier.targets_group.as_str()) {
+                    return Err(invalid(
+                        format!("{}.targets_group", field),
+                        "the modifier use a target group that does not exist in this effect",
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check every tag of the target groups is registered, separate from [`CardData::validate`] as it needs the world tags
+    pub fn validate_tags(&self, path: &Path, tags: &TagRegistry) -> Result<(), CardDataError> {
+        for (effect_index, effect) in self.effects.iter().enumerate() {
+            for (group_index, group) in effect.targets_groups.iter().enumerate() {
+                for (tag_index, tag) in group.tags.iter().enumerate() {
+                    if let Err(error) = tag.to_ids(tags) {
+                        return Err(CardDataError::Invalid {
+                            path: path.to_path_buf(),
+                            field: format!(
+                                "effects[{}].targets_groups[{}].tags[{}]",
+                                effect_index, group_index, tag_index
+                            ),
+                            reason: error.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CardRegistry {
+    /// Read and register a single card data file
+    /// The tags are only checked when the registry is given, see [`CardData::validate_tags`]
+    pub fn load_file(
+        &mut self,
+        path: &Path,
+        tags: Option<&TagRegistry>,
+    ) -> Result<CardId, CardDataError> {
+        let bytes = std::fs::read(path).map_err(|error| CardDataError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let data = CardData::from_bytes(path, &bytes)?;
+
+        if let Some(tags) = tags {
+            data.validate_tags(path, tags)?;
+        }
+
+        if let Some(registered) = self.get(&data.id) {
+            return Err(CardDataError::Invalid {
+                path: path.to_path_buf(),
+                field: "id".to_string(),
+                reason: format!(
+                    "the id {} is already used by the card '{}'",
+                    data.id.0, registered.name
+                ),
+            });
+        }
+
+        let id = data.id;
+
+        self.insert(data);
+        Ok(id)
+    }
+
+    /// Recursively register every card data file of the directory
+    /// Files are loaded in path order so duplicated ids always report the same file
+    /// Returns the errors of the files that couldn't be loaded, the valid files are still registered
+    pub fn load_dir(&mut self, dir: &Path, tags: Option<&TagRegistry>) -> Vec<CardDataError> {
+        let mut errors = Vec::new();
+        let mut files = Vec::new();
+
+        collect_card_files(dir, &mut files, &mut errors);
+        files.sort();
+
+        for file in files {
+            if let Err(e) = self.load_file(&file, tags) {
+                errors.push(e);
+            }
+        }
+        errors
+    }
+}
+
+fn collect_card_files(dir: &Path, files: &mut Vec<PathBuf>, errors: &mut Vec<CardDataError>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) => {
+            errors.push(CardDataError::Io {
+                path: dir.to_path_buf(),
+                error,
+            });
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.is_dir() {
+            collect_card_files(&path, files, errors);
+        } else if path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map_or(false, |extension| CARD_DATA_EXTENSIONS.contains(&extension))
+        {
+            files.push(path);
+        }
+    }
+}
@@ -1,7 +1,15 @@
+mod asset;
+mod loader;
+mod stats;
 mod visibility;
 
+pub use asset::*;
+pub use loader::*;
+pub use stats::*;
 pub use visibility::*;
 
+use std::path::Path;
+
 use bevy::{prelude::*, utils::HashMap};
 use bevy_replicon::{
     core::Replicated,
@@ -11,14 +19,21 @@ use bevy_replicon::{
 use epithet::utils::LevelEntity;
 use serde::{Deserialize, Serialize};
 
-use crate::{EffectId, EffectInstance, Effects};
+use crate::{
+    register_reflected_tags, EffectData, EffectInstance, EffectTriggerKind, Effects, ReflectTag,
+    TagRegistry, TriggerFilter,
+};
 
 pub fn card_plugin(app: &mut App) {
+    app.register_type::<Card>();
     app.replicate::<Card>();
 
     app.add_mapped_server_event::<CardAttributePacket>(ChannelKind::Ordered);
+    app.add_mapped_server_event::<CardHidePacket>(ChannelKind::Ordered);
     app.add_systems(Update, card_visibility_observer.before(ServerSet::Send));
-    app.add_systems(Update, on_card_visibility_event);
+    app.add_systems(Update, (on_card_visibility_event, on_card_hide_event));
+
+    app.observe(end_of_turn_modifiers_observer);
 
     app.replicate::<Card>();
 }
@@ -29,7 +44,8 @@ pub const CARD_HEIGHT: f32 = 0.088;
 #[derive(Default, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct CardId(pub u32);
 
-#[derive(Component, Serialize, Deserialize)]
+#[derive(Component, Reflect, Serialize, Deserialize)]
+#[reflect(Tag)]
 pub struct Card;
 
 #[derive(Component, Serialize, Deserialize, Clone)]
@@ -43,15 +59,43 @@ impl CardAttribute {
     }
 }
 
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct CardRegistry {
     cards: HashMap<CardId, CardData>,
 }
 
+impl CardRegistry {
+    pub fn insert(&mut self, data: CardData) -> Option<CardData> {
+        self.cards.insert(data.id, data)
+    }
+
+    pub fn remove(&mut self, id: &CardId) -> Option<CardData> {
+        self.cards.remove(id)
+    }
+
+    pub fn get(&self, id: &CardId) -> Option<&CardData> {
+        self.cards.get(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&CardId, &CardData)> {
+        self.cards.iter()
+    }
+}
+
+/// The definition of a card, read from the card data files (see [`CardRegistry::load_dir`])
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Debug)]
 pub struct CardData {
+    pub id: CardId,
     pub name: String,
+
+    #[serde(default)]
     pub description: String,
-    pub effects: Vec<EffectId>,
+
+    #[serde(default)]
+    pub stats: HashMap<String, i32>,
+
+    #[serde(default)]
+    pub effects: Vec<EffectData>,
 }
 
 impl CardData {
@@ -59,26 +103,80 @@ impl CardData {
         (
             CardBundle {
                 name: Name::new(self.name.clone()),
-                //TODO copy id and any needed data
+                card_attribute: CardAttribute::new(self.id),
+                card_stats: CardStats::new(self.stats.clone()),
                 ..default()
             },
-            Effects::new(
-                self.effects
-                    .iter()
-                    .map(|effect_id| EffectInstance::new(*effect_id))
-                    .collect(),
-            ),
+            self.create_effects(),
         )
     }
+
+    pub fn create_effects(&self) -> Effects {
+        Effects::new(self.effects.iter().map(EffectInstance::from_data).collect())
+    }
+
+    /// Insert the [`crate::EffectTrigger`] of the effects triggered by a game event
+    pub fn insert_effect_triggers(&self, card: &mut EntityWorldMut) {
+        let mut triggers: Vec<(EffectTriggerKind, Vec<(usize, TriggerFilter)>)> = Vec::new();
+
+        for (index, effect) in self.effects.iter().enumerate() {
+            let Some(kind) = effect.trigger else {
+                continue;
+            };
+            let filtered = (index, effect.trigger_filter);
+
+            match triggers.iter_mut().find(|(k, _)| *k == kind) {
+                Some((_, effects)) => effects.push(filtered),
+                None => triggers.push((kind, vec![filtered])),
+            }
+        }
+
+        for (kind, effects) in triggers {
+            kind.insert_trigger(card, effects);
+        }
+    }
 }
 
 pub trait CardPluginExt {
-    fn add_cards(&mut self) -> &mut Self;
+    /// Register every card data file found in the directory, invalid files are logged and skipped
+    /// The files are read before startup, once the reflected tags are registered so they can be checked
+    fn add_cards<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self;
+
+    /// Load the card data files of the asset folder as [`CardData`] assets, the [`CardRegistry`] follow the files changes when hot reloading is enabled
+    fn load_card_assets(&mut self, folder: &'static str) -> &mut Self;
 }
 
 impl CardPluginExt for App {
-    fn add_cards(&mut self) -> &mut Self {
-        let card_registry = self.world_mut().get_resource_mut::<CardRegistry>();
+    fn add_cards<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        let dir = dir.as_ref().to_path_buf();
+
+        self.init_resource::<CardRegistry>();
+        self.add_systems(
+            PreStartup,
+            (move |mut card_registry: ResMut<CardRegistry>, tags: Res<TagRegistry>| {
+                for error in card_registry.load_dir(&dir, Some(&tags)) {
+                    error!("Failed to load card data: {}", error);
+                }
+            })
+            .after(register_reflected_tags),
+        );
+
+        self
+    }
+
+    fn load_card_assets(&mut self, folder: &'static str) -> &mut Self {
+        self.init_asset::<CardData>();
+        self.init_asset_loader::<CardDataLoader>();
+        self.init_resource::<CardRegistry>();
+        self.add_event::<CardDataChanged>();
+
+        let folder = self.world().resource::<AssetServer>().load_folder(folder);
+
+        self.insert_resource(CardDataAssets::new(folder));
+        self.add_systems(
+            Update,
+            (card_data_asset_event_system, refresh_card_instances_system).chain(),
+        );
 
         self
     }
@@ -88,6 +186,7 @@ impl CardPluginExt for App {
 pub struct CardBundle {
     pub card: Card,
     pub card_attribute: CardAttribute,
+    pub card_stats: CardStats,
     pub card_visibility: CardVisibility,
     pub visibility: Visibility,
     pub inherited_visibility: InheritedVisibility,
@@ -103,6 +202,7 @@ impl Default for CardBundle {
             name: Name::new("Card"),
             card: Card,
             card_attribute: CardAttribute::new(CardId(0)),
+            card_stats: CardStats::default(),
             card_visibility: CardVisibility::new(vec![], false),
             visibility: Visibility::default(),
             inherited_visibility: InheritedVisibility::default(),
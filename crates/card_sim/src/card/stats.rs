@@ -0,0 +1,165 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{Board, StageEntered};
+
+/// The numeric stats of a card (ex: attack, defense), a missing stat is worth 0
+/// Server side only for now, replicating it would reveal the hidden cards through their stats
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct CardStats {
+    base: HashMap<String, i32>,
+    modifiers: Vec<StatModifier>,
+    /// The continuous effects layer, applied after the other modifiers and rebuilt when the field changes
+    continuous: Vec<StatModifier>,
+}
+
+/// A temporary change of a card stat
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StatModifier {
+    pub stat: String,
+    pub amount: i32,
+    pub duration: ModifierDuration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModifierDuration {
+    /// Removed when the next turn starts
+    EndOfTurn,
+    /// Applied by a continuous effect of the source while it is on the field
+    /// The timestamp is when the source entered the field, the older sources apply first
+    WhileOnField { source: Entity, timestamp: u64 },
+}
+
+impl ModifierDuration {
+    pub fn timestamp(&self) -> Option<u64> {
+        match self {
+            ModifierDuration::EndOfTurn => None,
+            ModifierDuration::WhileOnField { timestamp, .. } => Some(*timestamp),
+        }
+    }
+}
+
+impl CardStats {
+    pub fn new(base: HashMap<String, i32>) -> Self {
+        Self {
+            base,
+            modifiers: Vec::new(),
+            continuous: Vec::new(),
+        }
+    }
+
+    /// The value of the stat with every modifier applied, the continuous layer last
+    pub fn get(&self, stat: &str) -> i32 {
+        self.modifiers
+            .iter()
+            .chain(self.continuous.iter())
+            .filter(|modifier| modifier.stat == stat)
+            .fold(self.base(stat), |value, modifier| {
+                value.saturating_add(modifier.amount)
+            })
+    }
+
+    pub fn base(&self, stat: &str) -> i32 {
+        self.base.get(stat).copied().unwrap_or(0)
+    }
+
+    pub fn add_modifier(&mut self, modifier: StatModifier) {
+        self.modifiers.push(modifier);
+    }
+
+    pub fn remove_modifiers(&mut self, duration: ModifierDuration) {
+        self.modifiers
+            .retain(|modifier| modifier.duration != duration);
+    }
+
+    /// The modifiers of the continuous layer, ordered by timestamp
+    pub fn get_continuous_modifiers(&self) -> &[StatModifier] {
+        &self.continuous
+    }
+
+    /// Keep the layer ordered by timestamp, modifiers with the same timestamp stay in insertion order
+    pub(crate) fn add_continuous_modifier(&mut self, modifier: StatModifier) {
+        let timestamp = modifier.duration.timestamp();
+        let index = self
+            .continuous
+            .partition_point(|other| other.duration.timestamp() <= timestamp);
+
+        self.continuous.insert(index, modifier);
+    }
+
+    pub(crate) fn clear_continuous_modifiers(&mut self) {
+        self.continuous.clear();
+    }
+}
+
+/// Remove the end of turn modifiers of every card of the board when a new turn starts
+pub(crate) fn end_of_turn_modifiers_observer(
+    trigger: Trigger<StageEntered>,
+    boards: Query<&Board>,
+    mut stats: Query<&mut CardStats>,
+) {
+    let event = trigger.event();
+
+    let Ok(board) = boards.get(event.board) else {
+        return;
+    };
+
+    if !board.state.is_turn_start(&event.stage) {
+        return;
+    }
+
+    for entity in board.cache.get_entities().iter() {
+        if let Ok(mut card_stats) = stats.get_mut(*entity) {
+            card_stats.remove_modifiers(ModifierDuration::EndOfTurn);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn continuous(amount: i32, timestamp: u64) -> StatModifier {
+        StatModifier {
+            stat: "attack".to_string(),
+            amount,
+            duration: ModifierDuration::WhileOnField {
+                source: Entity::from_raw(timestamp as u32),
+                timestamp,
+            },
+        }
+    }
+
+    fn amounts(stats: &CardStats) -> Vec<i32> {
+        stats
+            .get_continuous_modifiers()
+            .iter()
+            .map(|modifier| modifier.amount)
+            .collect()
+    }
+
+    #[test]
+    fn continuous_modifiers_are_ordered_by_timestamp() {
+        let mut stats = CardStats::default();
+        stats.add_continuous_modifier(continuous(5, 5));
+        stats.add_continuous_modifier(continuous(2, 2));
+        stats.add_continuous_modifier(continuous(8, 8));
+
+        assert_eq!(amounts(&stats), vec![2, 5, 8]);
+        assert_eq!(stats.get("attack"), 15);
+    }
+
+    #[test]
+    fn same_timestamp_modifiers_keep_their_insertion_order() {
+        let mut stats = CardStats::default();
+        stats.add_continuous_modifier(continuous(1, 3));
+        stats.add_continuous_modifier(continuous(2, 1));
+        stats.add_continuous_modifier(continuous(3, 3));
+
+        assert_eq!(amounts(&stats), vec![2, 1, 3]);
+
+        stats.clear_continuous_modifiers();
+        assert!(stats.get_continuous_modifiers().is_empty());
+        assert_eq!(stats.get("attack"), 0);
+    }
+}
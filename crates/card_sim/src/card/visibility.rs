@@ -1,7 +1,7 @@
 use bevy::{ecs::entity::MapEntities, prelude::*};
 use bevy_replicon::{
     core::ClientId,
-    prelude::{SendMode, ToClients},
+    prelude::{ConnectedClients, SendMode, ToClients},
 };
 use epithet::units::{RenderRegistry, UnitRegistry};
 use serde::{Deserialize, Serialize};
@@ -31,7 +31,6 @@ impl CardVisibility {
 pub struct CardAttributePacket {
     pub card: Entity,
     pub attribute: CardAttribute,
-    pub remove: bool,
 }
 
 impl MapEntities for CardAttributePacket {
@@ -40,36 +39,97 @@ impl MapEntities for CardAttributePacket {
     }
 }
 
+/// Sent to the clients that could see the card and can't anymore, they forget its attribute
+#[derive(Event, Serialize, Deserialize)]
+pub struct CardHidePacket {
+    pub card: Entity,
+}
+
+impl MapEntities for CardHidePacket {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.card = entity_mapper.map_entity(self.card);
+    }
+}
+
+/// The visibility the clients last received for the card, used to hide it only from the clients that lost it
+#[derive(Component, Default)]
+pub(crate) struct SentCardVisibility {
+    visible_to: Vec<ClientId>,
+    visible_to_all: bool,
+}
+
 //TODO make it OnMutate observer when bevy supports it
 pub(crate) fn card_visibility_observer(
-    mut event_writter: EventWriter<ToClients<CardAttributePacket>>,
-    query: Query<
-        (Entity, &CardVisibility, &CardAttribute),
-        Or<(Added<CardAttribute>, Added<CardVisibility>)>,
+    mut commands: Commands,
+    mut attribute_writter: EventWriter<ToClients<CardAttributePacket>>,
+    mut hide_writter: EventWriter<ToClients<CardHidePacket>>,
+    connected_clients: Option<Res<ConnectedClients>>,
+    mut query: Query<
+        (
+            Entity,
+            &CardVisibility,
+            &CardAttribute,
+            Option<&mut SentCardVisibility>,
+        ),
+        Or<(Changed<CardAttribute>, Changed<CardVisibility>)>,
     >,
 ) {
-    for (entity, visibility, attribute) in query.iter() {
+    for (entity, visibility, attribute, sent) in query.iter_mut() {
+        // The clients that lost the card never receive its attribute again, so the order of the packets does not matter
+        if !visibility.visible_to_all {
+            let lost: Vec<ClientId> = match sent.as_deref() {
+                Some(sent) if sent.visible_to_all => connected_clients
+                    .iter()
+                    .flat_map(|clients| clients.iter_client_ids())
+                    .filter(|client_id| !visibility.visible_to.contains(client_id))
+                    .collect(),
+                Some(sent) => sent
+                    .visible_to
+                    .iter()
+                    .filter(|client_id| !visibility.visible_to.contains(client_id))
+                    .copied()
+                    .collect(),
+                None => Vec::new(),
+            };
+
+            for client_id in lost {
+                hide_writter.send(ToClients {
+                    mode: SendMode::Direct(client_id),
+                    event: CardHidePacket { card: entity },
+                });
+            }
+        }
+
         if visibility.visible_to_all {
-            event_writter.send(ToClients {
+            attribute_writter.send(ToClients {
                 mode: SendMode::Broadcast,
                 event: CardAttributePacket {
                     card: entity,
                     attribute: attribute.clone(),
-                    remove: false,
                 },
             });
         } else {
             for client_id in visibility.visible_to.iter() {
-                event_writter.send(ToClients {
+                attribute_writter.send(ToClients {
                     mode: SendMode::Direct(*client_id),
                     event: CardAttributePacket {
                         card: entity,
                         attribute: attribute.clone(),
-                        remove: false,
                     },
                 });
             }
         }
+
+        let state = SentCardVisibility {
+            visible_to: visibility.visible_to.clone(),
+            visible_to_all: visibility.visible_to_all,
+        };
+        match sent {
+            Some(mut sent) => *sent = state,
+            None => {
+                commands.entity(entity).insert(state);
+            }
+        }
     }
 }
 
@@ -80,21 +140,38 @@ pub(crate) fn on_card_visibility_event(
     units: Res<UnitRegistry>,
 ) {
     for packet in reader.read() {
-        if packet.remove {
-            commands.entity(packet.card).remove::<CardAttribute>();
-        } else {
-            let mut entity_commands: bevy::ecs::system::EntityCommands<'_> =
-                commands.entity(packet.card);
+        let mut entity_commands: bevy::ecs::system::EntityCommands<'_> =
+            commands.entity(packet.card);
 
-            entity_commands.insert(packet.attribute.clone());
+        entity_commands.insert(packet.attribute.clone());
 
-            #[cfg(feature = "render")]
-            {
-                entity_commands.despawn_descendants();
+        #[cfg(feature = "render")]
+        {
+            entity_commands.despawn_descendants();
 
-                //TODO just replace that with an event/observer/oneshot that recreate it somewhere else
-                renders.create_render(units.get_id::<Card>(), &mut commands, packet.card);
-            }
+            //TODO just replace that with an event/observer/oneshot that recreate it somewhere else
+            renders.create_render(units.get_id::<Card>(), &mut commands, packet.card);
+        }
+    }
+}
+
+pub(crate) fn on_card_hide_event(
+    mut commands: Commands,
+    mut reader: EventReader<CardHidePacket>,
+    renders: Res<RenderRegistry>,
+    units: Res<UnitRegistry>,
+) {
+    for packet in reader.read() {
+        let mut entity_commands: bevy::ecs::system::EntityCommands<'_> =
+            commands.entity(packet.card);
+
+        entity_commands.remove::<CardAttribute>();
+
+        #[cfg(feature = "render")]
+        {
+            entity_commands.despawn_descendants();
+
+            renders.create_render(units.get_id::<Card>(), &mut commands, packet.card);
         }
     }
 }
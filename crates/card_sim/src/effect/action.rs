@@ -0,0 +1,223 @@
+use bevy::{ecs::world::Command, prelude::*};
+
+use crate::{
+    Board, CardId, CardStats, DrawCommand, ModifierDuration, ModifyStatCommand, MoveToSlotCommand,
+    ReturnToHandCommand, SearchDeckCommand, SendToGraveyardCommand, StatModifier,
+};
+
+use super::{EffectAction, EffectActionData};
+
+/// The entities of the target group, logged as an error when the group was not resolved
+fn group_targets<'a>(data: &'a EffectActionData, targets_group: &str) -> &'a [Entity] {
+    match data.targets.get(targets_group) {
+        Some(targets) => targets,
+        None => {
+            error!(
+                "Effect action {} of {:?} use the target group '{}' which was not resolved, skipping",
+                data.action_index, data.self_entity, targets_group
+            );
+            &[]
+        }
+    }
+}
+
+/// The agents targeted by an agent action, the controller of the effect when there is no target group
+fn agent_targets(data: &EffectActionData, targets_group: &Option<String>) -> Vec<Entity> {
+    match targets_group {
+        Some(targets_group) => group_targets(data, targets_group).to_vec(),
+        None => vec![data.agent],
+    }
+}
+
+pub struct EffectActionDestroy {
+    targets_group: String,
+}
+
+impl EffectActionDestroy {
+    pub fn new(targets_group: String) -> Self {
+        Self { targets_group }
+    }
+}
+
+impl EffectAction for EffectActionDestroy {
+    fn execute(&self, commands: &mut Commands, data: &EffectActionData) {
+        for target in group_targets(data, &self.targets_group) {
+            commands.add(SendToGraveyardCommand(*target));
+        }
+    }
+}
+
+pub struct EffectActionDraw {
+    pub count: usize,
+    pub targets_group: Option<String>,
+}
+
+impl EffectAction for EffectActionDraw {
+    fn execute(&self, commands: &mut Commands, data: &EffectActionData) {
+        for agent in agent_targets(data, &self.targets_group) {
+            commands.add(DrawCommand::new(data.board, agent, self.count));
+        }
+    }
+}
+
+/// Without target group the damage are dealt to every opponent of the controller
+pub struct EffectActionDamage {
+    pub amount: i32,
+    pub targets_group: Option<String>,
+}
+
+impl EffectAction for EffectActionDamage {
+    fn execute(&self, commands: &mut Commands, data: &EffectActionData) {
+        let amount = self.amount;
+        let board_entity = data.board;
+
+        if let Some(targets_group) = &self.targets_group {
+            for agent in group_targets(data, targets_group) {
+                commands.add(ModifyStatCommand::life(board_entity, *agent, -amount));
+            }
+            return;
+        }
+
+        let controller = data.agent;
+
+        commands.add(move |world: &mut World| {
+            let Some(board) = world.get::<Board>(board_entity) else {
+                return;
+            };
+            let opponents: Vec<Entity> = board
+                .state
+                .agents
+                .iter()
+                .filter(|agent| **agent != controller && !board.state.is_defeated(**agent))
+                .copied()
+                .collect();
+
+            for agent in opponents {
+                ModifyStatCommand::life(board_entity, agent, -amount).apply(world);
+            }
+        });
+    }
+}
+
+pub struct EffectActionGainLife {
+    pub amount: i32,
+    pub targets_group: Option<String>,
+}
+
+impl EffectAction for EffectActionGainLife {
+    fn execute(&self, commands: &mut Commands, data: &EffectActionData) {
+        for agent in agent_targets(data, &self.targets_group) {
+            commands.add(ModifyStatCommand::life(data.board, agent, self.amount));
+        }
+    }
+}
+
+pub struct EffectActionReturnToHand {
+    pub targets_group: String,
+}
+
+impl EffectAction for EffectActionReturnToHand {
+    fn execute(&self, commands: &mut Commands, data: &EffectActionData) {
+        for target in group_targets(data, &self.targets_group) {
+            commands.add(ReturnToHandCommand(*target));
+        }
+    }
+}
+
+pub struct EffectActionSendToGraveyard {
+    pub targets_group: String,
+}
+
+impl EffectAction for EffectActionSendToGraveyard {
+    fn execute(&self, commands: &mut Commands, data: &EffectActionData) {
+        for target in group_targets(data, &self.targets_group) {
+            commands.add(SendToGraveyardCommand(*target));
+        }
+    }
+}
+
+/// Only the first target can take the slot, the others are ignored
+pub struct EffectActionMoveToSlot {
+    pub targets_group: String,
+    pub slot: IVec3,
+}
+
+impl EffectAction for EffectActionMoveToSlot {
+    fn execute(&self, commands: &mut Commands, data: &EffectActionData) {
+        if let Some(target) = group_targets(data, &self.targets_group).first() {
+            commands.add(MoveToSlotCommand {
+                board: data.board,
+                entity: *target,
+                slot: self.slot,
+            });
+        }
+    }
+}
+
+pub struct EffectActionModifyStat {
+    pub targets_group: String,
+    pub stat: String,
+    pub amount: i32,
+}
+
+impl EffectAction for EffectActionModifyStat {
+    fn execute(&self, commands: &mut Commands, data: &EffectActionData) {
+        for target in group_targets(data, &self.targets_group) {
+            let modifier = StatModifier {
+                stat: self.stat.clone(),
+                amount: self.amount,
+                duration: ModifierDuration::EndOfTurn,
+            };
+            let target = *target;
+
+            commands.add(move |world: &mut World| {
+                if let Some(mut stats) = world.get_mut::<CardStats>(target) {
+                    stats.add_modifier(modifier);
+                } else {
+                    warn!(
+                        "Effect tried to modify the stats of {:?} which has no stats, skipping",
+                        target
+                    );
+                }
+            });
+        }
+    }
+}
+
+pub struct EffectActionAddCounter {
+    pub counter: String,
+    pub amount: i32,
+    pub targets_group: Option<String>,
+}
+
+impl EffectAction for EffectActionAddCounter {
+    fn execute(&self, commands: &mut Commands, data: &EffectActionData) {
+        for agent in agent_targets(data, &self.targets_group) {
+            commands.add(ModifyStatCommand::counter(
+                data.board,
+                agent,
+                self.counter.clone(),
+                self.amount,
+            ));
+        }
+    }
+}
+
+pub struct EffectActionSearchDeck {
+    pub card: CardId,
+    pub count: usize,
+    pub targets_group: Option<String>,
+}
+
+impl EffectAction for EffectActionSearchDeck {
+    fn execute(&self, commands: &mut Commands, data: &EffectActionData) {
+        for agent in agent_targets(data, &self.targets_group) {
+            commands.add(SearchDeckCommand {
+                board: data.board,
+                agent,
+                card: self.card,
+                count: self.count,
+            });
+        }
+    }
+}
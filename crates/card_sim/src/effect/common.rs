@@ -12,4 +12,13 @@ pub struct When<T> {
     event: T,
 }
 
+/// Markers of the game events an effect can be triggered by, ex: `EffectTrigger<When<Summon>>`
 pub struct Summon;
+
+pub struct Destroy;
+
+pub struct Draw;
+
+pub struct StageEnter;
+
+pub struct Damage;
@@ -0,0 +1,257 @@
+use bevy::prelude::*;
+
+use crate::{AgentOwned, Board, CardStats, ModifierDuration, StageEntered, StatModifier};
+
+use super::{ContinuousModifierData, Effects, TargetGroup};
+
+/// Rebuild the continuous layer of the boards whose field changed since the last run
+/// Only the field membership is watched, a tag changing on a card on the field is picked up on the next field change
+pub(crate) fn continuous_effects_system(world: &mut World) {
+    let mut boards = world.query::<(Entity, &mut Board)>();
+    let dirty: Vec<Entity> = boards
+        .iter_mut(world)
+        .filter_map(|(entity, mut board)| {
+            std::mem::take(&mut board.cache.continuous_dirty).then_some(entity)
+        })
+        .collect();
+
+    for board in dirty {
+        refresh_continuous_effects(world, board);
+    }
+}
+
+/// The cards without owner are controlled by the turn agent, their continuous effects may target other cards on a new turn
+pub(crate) fn continuous_turn_start_observer(
+    trigger: Trigger<StageEntered>,
+    mut boards: Query<&mut Board>,
+) {
+    let event = trigger.event();
+
+    if let Ok(mut board) = boards.get_mut(event.board) {
+        if board.state.is_turn_start(&event.stage) {
+            board.cache.continuous_dirty = true;
+        }
+    }
+}
+
+/// Clear the continuous layer of every card of the board then apply the continuous effects of the cards on the field
+/// The sources apply from the oldest to the newest, so the order only depends on when they entered the field
+pub fn refresh_continuous_effects(world: &mut World, board_entity: Entity) {
+    let Some(board) = world.get::<Board>(board_entity) else {
+        return;
+    };
+
+    let entities: Vec<Entity> = board.cache.get_entities().iter().copied().collect();
+    let turn_agent = *board.state.get_current_turn_agent();
+
+    let mut sources: Vec<(u64, Entity)> = board
+        .cache
+        .field_timestamps
+        .iter()
+        .map(|(entity, timestamp)| (*timestamp, *entity))
+        .collect();
+    sources.sort();
+
+    for entity in entities {
+        if let Some(mut stats) = world.get_mut::<CardStats>(entity) {
+            stats.clear_continuous_modifiers();
+        }
+    }
+
+    for (timestamp, source) in sources {
+        let Some(effects) = world.get::<Effects>(source) else {
+            continue;
+        };
+
+        let continuous: Vec<(usize, Vec<ContinuousModifierData>, Vec<_>)> = effects
+            .iter()
+            .enumerate()
+            .filter(|(_, effect)| !effect.get_continuous().is_empty())
+            .map(|(index, effect)| {
+                (
+                    index,
+                    effect.get_continuous().to_vec(),
+                    effect.get_targets_groups().to_vec(),
+                )
+            })
+            .collect();
+
+        // Same as when its effects run, a card without owner is controlled by the turn agent
+        let Some(controller) = world
+            .get::<AgentOwned>(source)
+            .map(|owned| owned.0)
+            .or(turn_agent)
+        else {
+            continue;
+        };
+
+        for (effect_index, modifiers, targets_groups) in continuous {
+            for modifier in modifiers {
+                let Some(data) = targets_groups
+                    .iter()
+                    .find(|group| group.name == modifier.targets_group)
+                else {
+                    warn!(
+                        "Continuous effect {} of {:?} use the unknown target group {}, skipping",
+                        effect_index, source, modifier.targets_group
+                    );
+                    continue;
+                };
+
+                let targets = match TargetGroup::compile(world, data)
+                    .and_then(|mut group| group.candidates(world, board_entity, controller))
+                {
+                    Ok(targets) => targets,
+                    Err(e) => {
+                        warn!(
+                            "Continuous effect {} of {:?} could not find its targets: {}",
+                            effect_index, source, e
+                        );
+                        continue;
+                    }
+                };
+
+                for target in targets {
+                    if let Some(mut stats) = world.get_mut::<CardStats>(target) {
+                        stats.add_continuous_modifier(StatModifier {
+                            stat: modifier.stat.clone(),
+                            amount: modifier.amount,
+                            duration: ModifierDuration::WhileOnField { source, timestamp },
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoardSlot, Card, EffectData, EffectInstance, MoveToSlotCommand, OnBoard, OnField};
+    use bevy::ecs::world::Command;
+
+    fn setup() -> (World, Entity, Entity, Entity) {
+        let mut world = World::new();
+        let agent = world.spawn_empty().id();
+        let board = world.spawn(Board::with_seed(vec![agent], 0)).id();
+        let target = world
+            .spawn((
+                Card,
+                CardStats::default(),
+                AgentOwned(agent),
+                OnBoard(board),
+                OnField,
+            ))
+            .id();
+        (world, board, agent, target)
+    }
+
+    fn spawn_source(world: &mut World, board: Entity, agent: Entity, amount: i32) -> Entity {
+        let data: EffectData = serde_json::from_str(&format!(
+            r#"{{
+                "id": 0,
+                "targets_groups": [{{ "name": "units" }}],
+                "continuous": [{{ "targets_group": "units", "stat": "attack", "amount": {} }}]
+            }}"#,
+            amount
+        ))
+        .unwrap();
+
+        world
+            .spawn((
+                Card,
+                Effects::new(vec![EffectInstance::from_data(&data)]),
+                AgentOwned(agent),
+                OnBoard(board),
+                OnField,
+            ))
+            .id()
+    }
+
+    /// The sources of the continuous layer of the entity, in the order they apply
+    fn sources(world: &World, entity: Entity) -> Vec<Entity> {
+        world
+            .get::<CardStats>(entity)
+            .unwrap()
+            .get_continuous_modifiers()
+            .iter()
+            .filter_map(|modifier| match modifier.duration {
+                ModifierDuration::WhileOnField { source, .. } => Some(source),
+                ModifierDuration::EndOfTurn => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sources_apply_in_timestamp_order() {
+        let (mut world, board, agent, target) = setup();
+        let first = spawn_source(&mut world, board, agent, 1);
+        let second = spawn_source(&mut world, board, agent, 10);
+
+        refresh_continuous_effects(&mut world, board);
+        assert_eq!(sources(&world, target), vec![first, second]);
+        assert_eq!(world.get::<CardStats>(target).unwrap().get("attack"), 11);
+
+        // The second source is now the oldest, it applies first even though it got on the field last
+        world
+            .get_mut::<Board>(board)
+            .unwrap()
+            .cache
+            .restore_field_timestamp(second, 0);
+
+        refresh_continuous_effects(&mut world, board);
+        assert_eq!(sources(&world, target), vec![second, first]);
+    }
+
+    #[test]
+    fn sources_moved_to_a_slot_keep_their_place() {
+        let (mut world, board, agent, target) = setup();
+        let first = spawn_source(&mut world, board, agent, 1);
+        let second = spawn_source(&mut world, board, agent, 10);
+        world.spawn((BoardSlot(IVec3::ZERO, None), OnBoard(board)));
+
+        let timestamp = world
+            .get::<Board>(board)
+            .unwrap()
+            .cache
+            .get_field_timestamp(first);
+
+        MoveToSlotCommand {
+            board,
+            entity: first,
+            slot: IVec3::ZERO,
+        }
+        .apply(&mut world);
+
+        let cache = &world.get::<Board>(board).unwrap().cache;
+        assert!(!cache.is_on_field(first));
+        assert_eq!(cache.get_field_timestamp(first), timestamp);
+
+        refresh_continuous_effects(&mut world, board);
+        assert_eq!(sources(&world, target), vec![first, second]);
+    }
+
+    #[test]
+    fn layer_clears_when_the_source_leaves_the_field() {
+        let (mut world, board, agent, target) = setup();
+        let source = spawn_source(&mut world, board, agent, 1);
+
+        refresh_continuous_effects(&mut world, board);
+        assert_eq!(sources(&world, target), vec![source]);
+
+        world.entity_mut(source).remove::<OnField>();
+        assert_eq!(
+            world
+                .get::<Board>(board)
+                .unwrap()
+                .cache
+                .get_field_timestamp(source),
+            None
+        );
+
+        refresh_continuous_effects(&mut world, board);
+        assert!(sources(&world, target).is_empty());
+        assert_eq!(world.get::<CardStats>(target).unwrap().get("attack"), 0);
+    }
+}
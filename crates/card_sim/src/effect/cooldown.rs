@@ -0,0 +1,420 @@
+use std::fmt::{self, Display, Formatter};
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{Board, ChainLink, StageEntered};
+
+use super::{ActivationLimit, EffectCooldownData, Effects, LimitPeriod, LimitScope};
+
+/// Why an effect can't be activated yet
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum CooldownError {
+    /// Turns left before the effect can be activated again
+    Turns(u32),
+    /// Activations of other effects left before the effect can be activated again
+    Activations(u32),
+    LimitReached {
+        count: u32,
+        per: LimitPeriod,
+    },
+}
+
+impl Display for CooldownError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CooldownError::Turns(turns) => {
+                write!(f, "the effect is on cooldown for {} more turns", turns)
+            }
+            CooldownError::Activations(activations) => write!(
+                f,
+                "the effect is on cooldown for {} more activations",
+                activations
+            ),
+            CooldownError::LimitReached { count, per } => write!(
+                f,
+                "the effect can only be activated {} times per {:?}",
+                count, per
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CooldownError {}
+
+/// The cooldown state of an effect instance, the activations through the chain and the triggered effects count
+#[derive(Debug, Clone, Default)]
+pub struct EffectCooldown {
+    data: EffectCooldownData,
+    turns_left: u32,
+    activations_left: u32,
+    turn_uses: u32,
+    duel_uses: u32,
+    /// Activations still paying their costs, they count as a use until they go on the chain or get cancelled
+    reserved: u32,
+}
+
+impl EffectCooldown {
+    pub fn new(data: EffectCooldownData) -> Self {
+        Self { data, ..default() }
+    }
+
+    pub fn limit(&self) -> Option<ActivationLimit> {
+        self.data.limit
+    }
+
+    pub fn turns_left(&self) -> u32 {
+        self.turns_left
+    }
+
+    pub fn activations_left(&self) -> u32 {
+        self.activations_left
+    }
+
+    /// `name_uses` are the activations counted by [`NameActivations`], only used by the limits with the name scope
+    pub fn check(&self, name_uses: u32) -> Result<(), CooldownError> {
+        if self.turns_left > 0 {
+            return Err(CooldownError::Turns(self.turns_left));
+        }
+        if self.activations_left > 0 {
+            return Err(CooldownError::Activations(self.activations_left));
+        }
+
+        if let Some(limit) = self.data.limit {
+            let uses = match (limit.scope, limit.per) {
+                (LimitScope::Card, LimitPeriod::Turn) => self.turn_uses,
+                (LimitScope::Card, LimitPeriod::Duel) => self.duel_uses,
+                (LimitScope::Name, _) => name_uses,
+            };
+            if uses + self.reserved >= limit.count {
+                return Err(CooldownError::LimitReached {
+                    count: limit.count,
+                    per: limit.per,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Keep the cooldown state of the previous instance of the effect, ex: when its card data got reloaded
+    /// The cooldowns left are capped by the new durations
+    pub fn carry_over(&mut self, previous: &EffectCooldown) {
+        self.turns_left = previous.turns_left.min(self.data.turns);
+        self.activations_left = previous.activations_left.min(self.data.activations);
+        self.turn_uses = previous.turn_uses;
+        self.duel_uses = previous.duel_uses;
+        self.reserved = previous.reserved;
+    }
+
+    pub(crate) fn reserve(&mut self) {
+        self.reserved += 1;
+    }
+
+    pub(crate) fn release(&mut self) {
+        self.reserved = self.reserved.saturating_sub(1);
+    }
+
+    fn activated(&mut self) {
+        self.turns_left = self.data.turns;
+        self.activations_left = self.data.activations;
+        self.turn_uses += 1;
+        self.duel_uses += 1;
+    }
+
+    fn other_activated(&mut self) {
+        self.activations_left = self.activations_left.saturating_sub(1);
+    }
+
+    fn turn_started(&mut self) {
+        self.turns_left = self.turns_left.saturating_sub(1);
+        self.turn_uses = 0;
+    }
+}
+
+/// The activations of the effects with a name scoped limit, by agent, card name and effect index
+/// Every copy of a card share the same name, as do different cards with the same name
+#[derive(Debug, Default)]
+pub struct NameActivations {
+    turn: HashMap<(Entity, String, usize), u32>,
+    duel: HashMap<(Entity, String, usize), u32>,
+}
+
+impl NameActivations {
+    pub fn get(&self, agent: Entity, name: &str, effect_index: usize, per: LimitPeriod) -> u32 {
+        let uses = match per {
+            LimitPeriod::Turn => &self.turn,
+            LimitPeriod::Duel => &self.duel,
+        };
+        uses.get(&(agent, name.to_string(), effect_index))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn record(&mut self, agent: Entity, name: &str, effect_index: usize) {
+        *self
+            .turn
+            .entry((agent, name.to_string(), effect_index))
+            .or_insert(0) += 1;
+        *self
+            .duel
+            .entry((agent, name.to_string(), effect_index))
+            .or_insert(0) += 1;
+    }
+
+    pub(crate) fn end_turn(&mut self) {
+        self.turn.clear();
+    }
+}
+
+/// Check the cooldown of the effect of the link, with the name scoped activations of the board
+pub(crate) fn check_cooldown(
+    world: &World,
+    board: &Board,
+    link: &ChainLink,
+) -> Result<(), CooldownError> {
+    let Some(effect) = world
+        .get::<Effects>(link.card)
+        .and_then(|effects| effects.get_effect(link.effect_index))
+    else {
+        return Ok(());
+    };
+
+    let name_uses = match (effect.get_cooldown().limit(), world.get::<Name>(link.card)) {
+        (Some(limit), Some(name)) if limit.scope == LimitScope::Name => board
+            .state
+            .name_activations
+            .get(link.agent, name.as_str(), link.effect_index, limit.per),
+        _ => 0,
+    };
+
+    effect.get_cooldown().check(name_uses)
+}
+
+/// Count the activation of the effect of the link as a use while its costs are paid, see [`release_cooldown`]
+pub(crate) fn reserve_cooldown(world: &mut World, link: &ChainLink) {
+    if let Some(effect) = world
+        .get_mut::<Effects>(link.card)
+        .and_then(|effects| effects.into_inner().get_effect_mut(link.effect_index))
+    {
+        effect.get_cooldown_mut().reserve();
+    }
+}
+
+pub(crate) fn release_cooldown(world: &mut World, link: &ChainLink) {
+    if let Some(effect) = world
+        .get_mut::<Effects>(link.card)
+        .and_then(|effects| effects.into_inner().get_effect_mut(link.effect_index))
+    {
+        effect.get_cooldown_mut().release();
+    }
+}
+
+/// Put the effect of the link on cooldown and count down the activation cooldowns of the other effects of the board
+pub(crate) fn record_activation(world: &mut World, board_entity: Entity, link: &ChainLink) {
+    let Some(entities) = world.get::<Board>(board_entity).map(|board| {
+        board
+            .cache
+            .get_entities()
+            .iter()
+            .copied()
+            .collect::<Vec<_>>()
+    }) else {
+        return;
+    };
+
+    for entity in entities {
+        let Some(mut effects) = world.get_mut::<Effects>(entity) else {
+            continue;
+        };
+        for (index, effect) in effects.iter_mut().enumerate() {
+            if entity == link.card && index == link.effect_index {
+                effect.get_cooldown_mut().activated();
+            } else {
+                effect.get_cooldown_mut().other_activated();
+            }
+        }
+    }
+
+    let name_scoped = world
+        .get::<Effects>(link.card)
+        .and_then(|effects| effects.get_effect(link.effect_index))
+        .and_then(|effect| effect.get_cooldown().limit())
+        .map_or(false, |limit| limit.scope == LimitScope::Name);
+    let name = world
+        .get::<Name>(link.card)
+        .map(|name| name.as_str().to_string())
+        .filter(|_| name_scoped);
+
+    if let Some(name) = name {
+        if let Some(mut board) = world.get_mut::<Board>(board_entity) {
+            board
+                .state
+                .name_activations
+                .record(link.agent, &name, link.effect_index);
+        }
+    }
+}
+
+/// Count down the turn cooldowns of every effect of the board when a new turn starts
+pub(crate) fn cooldown_turn_start_observer(
+    trigger: Trigger<StageEntered>,
+    boards: Query<&Board>,
+    mut effects: Query<&mut Effects>,
+) {
+    let event = trigger.event();
+
+    let Ok(board) = boards.get(event.board) else {
+        return;
+    };
+
+    if !board.state.is_turn_start(&event.stage) {
+        return;
+    }
+
+    for entity in board.cache.get_entities().iter() {
+        if let Ok(mut card_effects) = effects.get_mut(*entity) {
+            for effect in card_effects.iter_mut() {
+                effect.get_cooldown_mut().turn_started();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cooldown(turns: u32, activations: u32, limit: Option<ActivationLimit>) -> EffectCooldown {
+        EffectCooldown::new(EffectCooldownData {
+            turns,
+            activations,
+            limit,
+        })
+    }
+
+    fn limit(count: u32, per: LimitPeriod, scope: LimitScope) -> Option<ActivationLimit> {
+        Some(ActivationLimit { count, per, scope })
+    }
+
+    #[test]
+    fn no_cooldown_can_always_activate() {
+        let mut effect = cooldown(0, 0, None);
+
+        assert_eq!(effect.check(0), Ok(()));
+        effect.activated();
+        assert_eq!(effect.check(0), Ok(()));
+    }
+
+    #[test]
+    fn turn_cooldown_count_down_on_turn_start() {
+        let mut effect = cooldown(2, 0, None);
+        effect.activated();
+
+        assert_eq!(effect.check(0), Err(CooldownError::Turns(2)));
+        effect.turn_started();
+        assert_eq!(effect.check(0), Err(CooldownError::Turns(1)));
+        effect.turn_started();
+        assert_eq!(effect.check(0), Ok(()));
+    }
+
+    #[test]
+    fn activation_cooldown_count_down_on_other_activations() {
+        let mut effect = cooldown(0, 1, None);
+        effect.activated();
+
+        assert_eq!(effect.check(0), Err(CooldownError::Activations(1)));
+        effect.other_activated();
+        assert_eq!(effect.check(0), Ok(()));
+    }
+
+    #[test]
+    fn card_limit_per_turn_reset_on_turn_start() {
+        let mut effect = cooldown(0, 0, limit(1, LimitPeriod::Turn, LimitScope::Card));
+        effect.activated();
+
+        assert_eq!(
+            effect.check(0),
+            Err(CooldownError::LimitReached {
+                count: 1,
+                per: LimitPeriod::Turn
+            })
+        );
+        effect.turn_started();
+        assert_eq!(effect.check(0), Ok(()));
+    }
+
+    #[test]
+    fn card_limit_per_duel_is_never_reset() {
+        let mut effect = cooldown(0, 0, limit(2, LimitPeriod::Duel, LimitScope::Card));
+        effect.activated();
+        effect.turn_started();
+        assert_eq!(effect.check(0), Ok(()));
+
+        effect.activated();
+        effect.turn_started();
+        assert_eq!(
+            effect.check(0),
+            Err(CooldownError::LimitReached {
+                count: 2,
+                per: LimitPeriod::Duel
+            })
+        );
+    }
+
+    #[test]
+    fn name_limit_use_the_given_uses() {
+        let mut effect = cooldown(0, 0, limit(1, LimitPeriod::Turn, LimitScope::Name));
+        effect.activated();
+
+        // Only the activations of the name count, not the ones of this instance
+        assert_eq!(effect.check(0), Ok(()));
+        assert_eq!(
+            effect.check(1),
+            Err(CooldownError::LimitReached {
+                count: 1,
+                per: LimitPeriod::Turn
+            })
+        );
+    }
+
+    #[test]
+    fn name_activations_are_shared_by_name() {
+        let agent = Entity::from_raw(1);
+        let mut activations = NameActivations::default();
+        activations.record(agent, "John", 0);
+
+        assert_eq!(activations.get(agent, "John", 0, LimitPeriod::Turn), 1);
+        assert_eq!(activations.get(agent, "John", 1, LimitPeriod::Turn), 0);
+        assert_eq!(activations.get(agent, "Jane", 0, LimitPeriod::Turn), 0);
+
+        activations.end_turn();
+        assert_eq!(activations.get(agent, "John", 0, LimitPeriod::Turn), 0);
+        assert_eq!(activations.get(agent, "John", 0, LimitPeriod::Duel), 1);
+    }
+
+    #[test]
+    fn reserved_uses_count_until_released() {
+        let mut effect = cooldown(0, 0, limit(1, LimitPeriod::Turn, LimitScope::Card));
+        effect.reserve();
+
+        assert_eq!(
+            effect.check(0),
+            Err(CooldownError::LimitReached {
+                count: 1,
+                per: LimitPeriod::Turn
+            })
+        );
+        effect.release();
+        assert_eq!(effect.check(0), Ok(()));
+    }
+
+    #[test]
+    fn carried_over_cooldowns_are_capped() {
+        let mut previous = cooldown(3, 0, None);
+        previous.activated();
+        let mut reloaded = cooldown(1, 0, None);
+        reloaded.carry_over(&previous);
+
+        assert_eq!(reloaded.check(0), Err(CooldownError::Turns(1)));
+    }
+}
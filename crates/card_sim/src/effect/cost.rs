@@ -0,0 +1,615 @@
+use std::fmt::{self, Display, Formatter};
+
+use bevy::{ecs::world::Command, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agent_client_id, push_activation, put_in_graveyard, send_rejection, AgentOwned, AgentStat,
+    AgentStats, Board, BoardQuery, BoardQueryLoc, BoardSlot, Card, ChainError, ChainLink,
+    ModifyStatCommand, OnField, OnGraveyard, OnSlot, RejectionReason, ReturnToHandCommand,
+    StatModification,
+};
+
+use super::{
+    release_cooldown, request_selection, reserve_cooldown, EffectCostKind, Effects,
+    PendingSelection,
+};
+
+/// Why the costs of an effect can't be paid
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum CostError {
+    /// Not enough cards in the hand or on the field, `cost_index` is the first cost that can't be paid
+    NotEnoughCards {
+        cost_index: usize,
+        found: usize,
+        required: usize,
+    },
+    /// Paying life can't take the agent to 0
+    NotEnoughLife {
+        life: i32,
+        required: i32,
+    },
+    NotEnoughCounters {
+        counter: String,
+        found: i32,
+        required: i32,
+    },
+    NoStats,
+}
+
+impl Display for CostError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CostError::NotEnoughCards {
+                cost_index,
+                found,
+                required,
+            } => write!(
+                f,
+                "the cost {} needs {} cards but only {} can be used",
+                cost_index, required, found
+            ),
+            CostError::NotEnoughLife { life, required } => {
+                write!(f, "the agent has {} life and can't pay {}", life, required)
+            }
+            CostError::NotEnoughCounters {
+                counter,
+                found,
+                required,
+            } => write!(
+                f,
+                "the agent has {} {} counters but {} are needed",
+                found, counter, required
+            ),
+            CostError::NoStats => write!(f, "the agent has no stats to pay with"),
+        }
+    }
+}
+
+impl std::error::Error for CostError {}
+
+/// The cards the agent of the link can use for a discard or a tribute, never the activated card itself
+fn cost_candidates(
+    world: &World,
+    board_entity: Entity,
+    link: &ChainLink,
+    cost: &EffectCostKind,
+) -> Vec<Entity> {
+    let owner = AgentOwned(link.agent);
+    let location = match cost {
+        EffectCostKind::Discard { .. } => BoardQueryLoc::Hand(owner),
+        EffectCostKind::Tribute { .. } => BoardQueryLoc::Field(Some(owner)),
+        _ => return Vec::new(),
+    };
+
+    world
+        .get::<Board>(board_entity)
+        .map(|board| {
+            BoardQuery::query(board, &[location])
+                .into_iter()
+                .filter(|entity| *entity != link.card && world.get::<Card>(*entity).is_some())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Check every cost of the effect of the link can be paid together, nothing is paid
+pub(crate) fn check_costs(
+    world: &World,
+    board_entity: Entity,
+    link: &ChainLink,
+) -> Result<(), CostError> {
+    let Some(costs) = world
+        .get::<Effects>(link.card)
+        .and_then(|effects| effects.get_effect(link.effect_index))
+        .map(|effect| effect.get_costs())
+    else {
+        return Ok(());
+    };
+
+    check_cost_list(world, board_entity, link, costs, 0)
+}
+
+/// Check the costs can be paid together, `first_index` is the index of the first of them in the effect costs
+fn check_cost_list(
+    world: &World,
+    board_entity: Entity,
+    link: &ChainLink,
+    costs: &[EffectCostKind],
+    first_index: usize,
+) -> Result<(), CostError> {
+    let (mut discards, mut tributes, mut life) = (0, 0, 0);
+    let mut counters: HashMap<&str, i32> = HashMap::new();
+
+    for (cost_index, cost) in (first_index..).zip(costs.iter()) {
+        let required = match cost {
+            EffectCostKind::Discard { count } => {
+                discards += count;
+                discards
+            }
+            EffectCostKind::Tribute { count } => {
+                tributes += count;
+                tributes
+            }
+            EffectCostKind::PayLife { amount } => {
+                life += amount;
+                continue;
+            }
+            EffectCostKind::RemoveCounters { counter, amount } => {
+                *counters.entry(counter.as_str()).or_insert(0) += amount;
+                continue;
+            }
+        };
+
+        let found = cost_candidates(world, board_entity, link, cost).len();
+        if found < required {
+            return Err(CostError::NotEnoughCards {
+                cost_index,
+                found,
+                required,
+            });
+        }
+    }
+
+    if life == 0 && counters.is_empty() {
+        return Ok(());
+    }
+
+    let stats = world
+        .get::<AgentStats>(link.agent)
+        .ok_or(CostError::NoStats)?;
+
+    if life > 0 && stats.life() <= life {
+        return Err(CostError::NotEnoughLife {
+            life: stats.life(),
+            required: life,
+        });
+    }
+    for (counter, amount) in counters {
+        if stats.counter(counter) < amount {
+            return Err(CostError::NotEnoughCounters {
+                counter: counter.to_string(),
+                found: stats.counter(counter),
+                required: amount,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// A cost already paid, given back if the activation get cancelled
+#[derive(Debug)]
+enum PaidCost {
+    Discarded(Vec<Entity>),
+    /// The cards with the slot they were on, if any, and when they entered the field
+    Tributed(Vec<(Entity, Option<Entity>, Option<u64>)>),
+    Life(i32),
+    Counters {
+        counter: String,
+        amount: i32,
+    },
+}
+
+/// An activation paying its costs, it goes on the chain once every cost is paid
+pub(crate) struct PendingActivation {
+    board: Entity,
+    link: ChainLink,
+    costs: Vec<EffectCostKind>,
+    next_cost: usize,
+    paid: Vec<PaidCost>,
+    /// The activation counts as a use of the effect cooldown until it goes on the chain or get cancelled
+    reserved: bool,
+}
+
+impl PendingActivation {
+    pub(crate) fn board(&self) -> Entity {
+        self.board
+    }
+
+    pub(crate) fn agent(&self) -> Entity {
+        self.link.agent
+    }
+
+    /// Check the costs left to pay can still be paid, the board may have changed while the agent was choosing
+    fn check_remaining_costs(&self, world: &World) -> Result<(), CostError> {
+        check_cost_list(
+            world,
+            self.board,
+            &self.link,
+            &self.costs[self.next_cost..],
+            self.next_cost,
+        )
+    }
+
+    /// The cards that can pay the current discard or tribute cost right now
+    pub(crate) fn candidates(&self, world: &World) -> Vec<Entity> {
+        self.costs
+            .get(self.next_cost)
+            .map(|cost| cost_candidates(world, self.board, &self.link, cost))
+            .unwrap_or_default()
+    }
+
+    /// Send the chosen cards of the current discard or tribute cost to the graveyard
+    /// The cards are checked again since the board may have changed while the agent was choosing
+    pub(crate) fn pay_cards(
+        &mut self,
+        world: &mut World,
+        cards: Vec<Entity>,
+    ) -> Result<(), CostError> {
+        let Some(cost) = self.costs.get(self.next_cost).cloned() else {
+            return Ok(());
+        };
+        let candidates = cost_candidates(world, self.board, &self.link, &cost);
+        let required = match cost {
+            EffectCostKind::Discard { count } | EffectCostKind::Tribute { count } => count,
+            _ => 0,
+        };
+
+        let found = cards
+            .iter()
+            .filter(|card| candidates.contains(card))
+            .count();
+        if found < cards.len() || found < required {
+            return Err(CostError::NotEnoughCards {
+                cost_index: self.next_cost,
+                found,
+                required: required.max(cards.len()),
+            });
+        }
+
+        let mut tributed = Vec::new();
+        for card in cards.iter() {
+            let slot = world.get::<OnSlot>(*card).map(|on_slot| on_slot.0);
+            let timestamp = world
+                .get::<Board>(self.board)
+                .and_then(|board| board.cache.get_field_timestamp(*card));
+            put_in_graveyard(&mut world.entity_mut(*card));
+            tributed.push((*card, slot, timestamp));
+        }
+
+        self.paid.push(match cost {
+            EffectCostKind::Tribute { .. } => PaidCost::Tributed(tributed),
+            _ => PaidCost::Discarded(cards),
+        });
+        self.next_cost += 1;
+        Ok(())
+    }
+}
+
+/// Pay the costs of the effect of a validated link then put it on the chain, server side only
+pub(crate) fn start_activation(world: &mut World, board: Entity, link: ChainLink) {
+    let costs = world
+        .get::<Effects>(link.card)
+        .and_then(|effects| effects.get_effect(link.effect_index))
+        .map(|effect| effect.get_costs().to_vec())
+        .unwrap_or_default();
+
+    reserve_cooldown(world, &link);
+    continue_activation(
+        world,
+        PendingActivation {
+            board,
+            link,
+            costs,
+            next_cost: 0,
+            paid: Vec::new(),
+            reserved: true,
+        },
+    );
+}
+
+/// Pay the remaining costs, the activation is put on hold when the agent has cards to choose
+pub(crate) fn continue_activation(world: &mut World, mut activation: PendingActivation) {
+    let (board, agent) = (activation.board, activation.link.agent);
+
+    if let Err(error) = activation.check_remaining_costs(world) {
+        cancel_activation(world, activation, Some(ChainError::Cost(error)));
+        return;
+    }
+
+    while let Some(cost) = activation.costs.get(activation.next_cost).cloned() {
+        match cost {
+            EffectCostKind::PayLife { amount } => {
+                ModifyStatCommand::new(
+                    board,
+                    agent,
+                    AgentStat::Life,
+                    StatModification::Pay(amount),
+                )
+                .apply(world);
+                activation.paid.push(PaidCost::Life(amount));
+                activation.next_cost += 1;
+            }
+            EffectCostKind::RemoveCounters { counter, amount } => {
+                ModifyStatCommand::new(
+                    board,
+                    agent,
+                    AgentStat::Counter(counter.clone()),
+                    StatModification::Pay(amount),
+                )
+                .apply(world);
+                activation.paid.push(PaidCost::Counters { counter, amount });
+                activation.next_cost += 1;
+            }
+            EffectCostKind::Discard { count } | EffectCostKind::Tribute { count } => {
+                let candidates = cost_candidates(world, board, &activation.link, &cost);
+
+                if candidates.len() < count {
+                    let error = CostError::NotEnoughCards {
+                        cost_index: activation.next_cost,
+                        found: candidates.len(),
+                        required: count,
+                    };
+                    cancel_activation(world, activation, Some(ChainError::Cost(error)));
+                    return;
+                }
+
+                // There is no choice to make when every candidate is needed, agents without a client get the first ones
+                if candidates.len() > count && agent_client_id(world, agent).is_some() {
+                    let group = match cost {
+                        EffectCostKind::Tribute { .. } => "tribute",
+                        _ => "discard",
+                    };
+                    request_selection(
+                        world,
+                        PendingSelection::Activation(activation),
+                        group,
+                        candidates,
+                        count,
+                        count,
+                    );
+                    return;
+                }
+
+                let cards = candidates.into_iter().take(count).collect();
+                if let Err(error) = activation.pay_cards(world, cards) {
+                    cancel_activation(world, activation, Some(ChainError::Cost(error)));
+                    return;
+                }
+            }
+        }
+    }
+
+    // The use is recorded for good once the link is on the chain
+    release_cooldown(world, &activation.link);
+    activation.reserved = false;
+
+    if let Err(error) = push_activation(world, activation.board, activation.link) {
+        warn!(
+            "Effect {} of {:?} could not go on the chain after paying its costs: {}",
+            activation.link.effect_index, activation.link.card, error
+        );
+        cancel_activation(world, activation, Some(error));
+    }
+}
+
+/// Give back every paid cost of the activation, the reason is sent to the agent as a rejection if any
+pub(crate) fn cancel_activation(
+    world: &mut World,
+    activation: PendingActivation,
+    reason: Option<ChainError>,
+) {
+    let PendingActivation {
+        board,
+        link,
+        paid,
+        reserved,
+        ..
+    } = activation;
+
+    if reserved {
+        release_cooldown(world, &link);
+    }
+
+    for cost in paid.into_iter().rev() {
+        match cost {
+            PaidCost::Discarded(cards) => {
+                for card in cards {
+                    ReturnToHandCommand(card).apply(world);
+                }
+            }
+            PaidCost::Tributed(cards) => {
+                for (card, slot, timestamp) in cards {
+                    // The slot may have been taken while the agent was choosing
+                    let free_slot = slot.filter(|slot| {
+                        world
+                            .get::<BoardSlot>(*slot)
+                            .map_or(false, |board_slot| board_slot.1.is_none())
+                    });
+                    let Some(mut entity) = world.get_entity_mut(card) else {
+                        continue;
+                    };
+
+                    entity.remove::<OnGraveyard>();
+                    match free_slot {
+                        Some(slot) => entity.insert(OnSlot(slot)),
+                        None => entity.insert(OnField),
+                    };
+
+                    // The card never really left the field, its continuous effects keep their order
+                    if let (Some(timestamp), Some(mut board)) =
+                        (timestamp, world.get_mut::<Board>(board))
+                    {
+                        board.cache.restore_field_timestamp(card, timestamp);
+                    }
+                }
+            }
+            PaidCost::Life(amount) => {
+                ModifyStatCommand::life(board, link.agent, amount).apply(world)
+            }
+            PaidCost::Counters { counter, amount } => {
+                ModifyStatCommand::counter(board, link.agent, counter, amount).apply(world)
+            }
+        }
+    }
+
+    if let Some(reason) = reason {
+        send_rejection(world, board, link.agent, RejectionReason::Chain(reason));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        check_cooldown, ActivationLimit, BoardStage, EffectCooldownData, EffectData,
+        EffectInstance, LimitPeriod, LimitScope, OnBoard, OnHand, NORMAL_EFFECT_SPEED,
+    };
+
+    fn setup(life: i32, costs: Vec<EffectCostKind>) -> (World, Entity, ChainLink) {
+        let mut world = World::new();
+        let agent = world.spawn(AgentStats::new(life)).id();
+        let board = world.spawn(Board::with_seed(vec![agent], 0)).id();
+
+        let mut data: EffectData = serde_json::from_str(r#"{ "id": 0 }"#).unwrap();
+        data.costs = costs;
+        data.cooldown = Some(EffectCooldownData {
+            turns: 0,
+            activations: 0,
+            limit: Some(ActivationLimit {
+                count: 1,
+                per: LimitPeriod::Turn,
+                scope: LimitScope::Card,
+            }),
+        });
+        let card = world
+            .spawn((
+                Card,
+                Effects::new(vec![EffectInstance::from_data(&data)]),
+                AgentOwned(agent),
+                OnBoard(board),
+                OnHand,
+            ))
+            .id();
+
+        let link = ChainLink {
+            card,
+            effect_index: 0,
+            agent,
+            speed: NORMAL_EFFECT_SPEED,
+        };
+        (world, board, link)
+    }
+
+    fn stats(world: &World, link: &ChainLink) -> (i32, i32) {
+        let stats = world.get::<AgentStats>(link.agent).unwrap();
+        (stats.life(), stats.counter("mana"))
+    }
+
+    #[test]
+    fn life_costs_are_checked_together() {
+        let (world, board, link) = setup(1000, vec![EffectCostKind::PayLife { amount: 500 }]);
+        assert_eq!(check_costs(&world, board, &link), Ok(()));
+
+        let costs = vec![
+            EffectCostKind::PayLife { amount: 600 },
+            EffectCostKind::PayLife { amount: 600 },
+        ];
+        let (world, board, link) = setup(1000, costs);
+        assert_eq!(
+            check_costs(&world, board, &link),
+            Err(CostError::NotEnoughLife {
+                life: 1000,
+                required: 1200
+            })
+        );
+    }
+
+    #[test]
+    fn paying_life_can_not_take_the_agent_to_zero() {
+        let (world, board, link) = setup(1000, vec![EffectCostKind::PayLife { amount: 1000 }]);
+
+        assert!(matches!(
+            check_costs(&world, board, &link),
+            Err(CostError::NotEnoughLife { .. })
+        ));
+    }
+
+    #[test]
+    fn missing_counters_can_not_be_paid() {
+        let costs = vec![EffectCostKind::RemoveCounters {
+            counter: "mana".to_string(),
+            amount: 1,
+        }];
+        let (world, board, link) = setup(1000, costs);
+
+        assert_eq!(
+            check_costs(&world, board, &link),
+            Err(CostError::NotEnoughCounters {
+                counter: "mana".to_string(),
+                found: 0,
+                required: 1
+            })
+        );
+    }
+
+    #[test]
+    fn the_activated_card_can_not_pay_its_own_discard() {
+        let (mut world, board, link) = setup(1000, vec![EffectCostKind::Discard { count: 1 }]);
+        assert_eq!(
+            check_costs(&world, board, &link),
+            Err(CostError::NotEnoughCards {
+                cost_index: 0,
+                found: 0,
+                required: 1
+            })
+        );
+
+        world.spawn((Card, AgentOwned(link.agent), OnBoard(board), OnHand));
+        assert_eq!(check_costs(&world, board, &link), Ok(()));
+    }
+
+    #[test]
+    fn paid_activation_goes_on_the_chain() {
+        let costs = vec![
+            EffectCostKind::PayLife { amount: 300 },
+            EffectCostKind::RemoveCounters {
+                counter: "mana".to_string(),
+                amount: 2,
+            },
+        ];
+        let (mut world, board, link) = setup(1000, costs);
+        ModifyStatCommand::counter(board, link.agent, "mana", 2).apply(&mut world);
+        {
+            let mut board = world.get_mut::<Board>(board).unwrap();
+            board.state.game_start();
+            board.state.stage = BoardStage::Main;
+        }
+
+        start_activation(&mut world, board, link);
+
+        assert_eq!(stats(&world, &link), (700, 0));
+        let board = world.get::<Board>(board).unwrap();
+        assert_eq!(
+            board.state.get_chain().and_then(|tree| tree.last_link()),
+            Some(&link)
+        );
+    }
+
+    #[test]
+    fn cancelled_activation_gives_back_its_costs_and_cooldown() {
+        let costs = vec![
+            EffectCostKind::PayLife { amount: 300 },
+            EffectCostKind::RemoveCounters {
+                counter: "mana".to_string(),
+                amount: 2,
+            },
+        ];
+        let (mut world, board, link) = setup(1000, costs);
+        ModifyStatCommand::counter(board, link.agent, "mana", 2).apply(&mut world);
+
+        // The match did not start, the link can't go on the chain once the costs are paid
+        start_activation(&mut world, board, link);
+
+        assert_eq!(stats(&world, &link), (1000, 2));
+        assert!(world
+            .get::<Board>(board)
+            .unwrap()
+            .state
+            .get_chain()
+            .is_none());
+
+        let board = world.get::<Board>(board).unwrap();
+        assert_eq!(check_cooldown(&world, board, &link), Ok(()));
+    }
+}
@@ -0,0 +1,323 @@
+use bevy::math::IVec3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CardId, EffectAction, EffectActionAddCounter, EffectActionDamage, EffectActionDestroy,
+    EffectActionDraw, EffectActionGainLife, EffectActionModifyStat, EffectActionMoveToSlot,
+    EffectActionReturnToHand, EffectActionSearchDeck, EffectActionSendToGraveyard, EffectId,
+    RuntimeQueryTag, TargetOwner, TargetZone, TriggerFilter, NORMAL_EFFECT_SPEED,
+};
+
+/// The definition of an effect as written in a card data file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EffectData {
+    /// The registered effect this definition instantiate
+    pub id: EffectId,
+
+    /// Designer facing name of the effect, only used for debugging and error reporting
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Chain speed of the effect, see [`crate::NORMAL_EFFECT_SPEED`]
+    #[serde(default = "default_effect_speed")]
+    pub speed: i32,
+
+    #[serde(default)]
+    pub cooldown: Option<EffectCooldownData>,
+
+    #[serde(default)]
+    pub targets_groups: Vec<TargetGroupData>,
+
+    #[serde(default)]
+    pub value: i32,
+
+    /// Paid in order when the effect is activated, before it goes on the chain
+    #[serde(default)]
+    pub costs: Vec<EffectCostKind>,
+
+    /// The built-in actions run when the effect resolve, before the registered effect system
+    #[serde(default)]
+    pub actions: Vec<EffectActionKind>,
+
+    /// Applied while the card is on the field, without going through the chain
+    /// An effect with only continuous modifiers can't be activated
+    #[serde(default)]
+    pub continuous: Vec<ContinuousModifierData>,
+
+    /// The game event triggering the effect on its own, effects without one have to be activated
+    #[serde(default)]
+    pub trigger: Option<EffectTriggerKind>,
+
+    /// Who has to cause the trigger event, every subject by default
+    #[serde(default)]
+    pub trigger_filter: TriggerFilter,
+}
+
+/// The game events a card data can trigger its effects with, see [`crate::GameEvent`]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectTriggerKind {
+    Summon,
+    Destroy,
+    Draw,
+    StageEnter,
+    Damage,
+}
+
+/// A cost as written in a card data file, ex: `{ "cost": "pay_life", "amount": 500 }`
+/// The cards of the discard and tribute costs are chosen by the agent paying them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "cost", rename_all = "snake_case")]
+pub enum EffectCostKind {
+    /// Send cards from the hand to the graveyard, the activated card can't be used
+    Discard {
+        count: usize,
+    },
+    PayLife {
+        amount: i32,
+    },
+    /// Send cards from the field to the graveyard, the activated card can't be used
+    Tribute {
+        count: usize,
+    },
+    RemoveCounters {
+        counter: String,
+        amount: i32,
+    },
+}
+
+/// A stat change of a continuous effect, ex: `{ "targets_group": "units", "stat": "attack", "amount": 1 }`
+/// Every candidate of the group is affected, the min and max of the group are ignored
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ContinuousModifierData {
+    pub targets_group: String,
+    pub stat: String,
+    pub amount: i32,
+}
+
+fn default_effect_speed() -> i32 {
+    NORMAL_EFFECT_SPEED
+}
+
+/// When an effect can be activated again, see [`crate::EffectCooldown`]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct EffectCooldownData {
+    /// Turns to wait after an activation, counted down at the start of every turn
+    #[serde(default)]
+    pub turns: u32,
+
+    /// Activations of other effects on the board to wait after an activation
+    #[serde(default)]
+    pub activations: u32,
+
+    /// ex: once per turn, `{ "per": "turn" }`
+    #[serde(default)]
+    pub limit: Option<ActivationLimit>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ActivationLimit {
+    #[serde(default = "default_limit_count")]
+    pub count: u32,
+    pub per: LimitPeriod,
+    #[serde(default)]
+    pub scope: LimitScope,
+}
+
+fn default_limit_count() -> u32 {
+    1
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitPeriod {
+    Turn,
+    Duel,
+}
+
+/// What the limit counts the activations of
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitScope {
+    /// This card only, other copies have their own count
+    #[default]
+    Card,
+    /// Every card with the same name controlled by the agent
+    Name,
+}
+
+/// A named group of targets, the tags are the components the targets need to match
+/// The candidates are looked up in the zones then filtered by the tags, see [`crate::TargetGroup`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TargetGroupData {
+    pub name: String,
+
+    #[serde(default)]
+    pub tags: Vec<RuntimeQueryTag>,
+
+    #[serde(default = "default_target_zones")]
+    pub zones: Vec<TargetZone>,
+
+    #[serde(default)]
+    pub owner: TargetOwner,
+
+    /// The effect can't resolve with less targets than this
+    #[serde(default = "default_target_count")]
+    pub min: usize,
+
+    #[serde(default = "default_target_count")]
+    pub max: usize,
+}
+
+fn default_target_zones() -> Vec<TargetZone> {
+    vec![TargetZone::Field]
+}
+
+fn default_target_count() -> usize {
+    1
+}
+
+/// A built-in effect action as written in a card data file, ex: `{ "action": "draw", "count": 2 }`
+/// Agent actions without `targets_group` apply to the controller of the effect (the opponents for damage)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum EffectActionKind {
+    Destroy {
+        targets_group: String,
+    },
+    Draw {
+        count: usize,
+        #[serde(default)]
+        targets_group: Option<String>,
+    },
+    Damage {
+        amount: i32,
+        #[serde(default)]
+        targets_group: Option<String>,
+    },
+    GainLife {
+        amount: i32,
+        #[serde(default)]
+        targets_group: Option<String>,
+    },
+    ReturnToHand {
+        targets_group: String,
+    },
+    SendToGraveyard {
+        targets_group: String,
+    },
+    MoveToSlot {
+        targets_group: String,
+        slot: IVec3,
+    },
+    /// The modification last until the end of the turn
+    ModifyStat {
+        targets_group: String,
+        stat: String,
+        amount: i32,
+    },
+    AddCounter {
+        counter: String,
+        amount: i32,
+        #[serde(default)]
+        targets_group: Option<String>,
+    },
+    SearchDeck {
+        card: CardId,
+        #[serde(default = "default_search_count")]
+        count: usize,
+        #[serde(default)]
+        targets_group: Option<String>,
+    },
+}
+
+fn default_search_count() -> usize {
+    1
+}
+
+impl EffectActionKind {
+    /// The target group the action read, used to validate the card data
+    pub fn targets_group(&self) -> Option<&str> {
+        match self {
+            EffectActionKind::Destroy { targets_group }
+            | EffectActionKind::ReturnToHand { targets_group }
+            | EffectActionKind::SendToGraveyard { targets_group }
+            | EffectActionKind::MoveToSlot { targets_group, .. }
+            | EffectActionKind::ModifyStat { targets_group, .. } => Some(targets_group),
+            EffectActionKind::Draw { targets_group, .. }
+            | EffectActionKind::Damage { targets_group, .. }
+            | EffectActionKind::GainLife { targets_group, .. }
+            | EffectActionKind::AddCounter { targets_group, .. }
+            | EffectActionKind::SearchDeck { targets_group, .. } => targets_group.as_deref(),
+        }
+    }
+
+    pub fn create_action(&self) -> Box<dyn EffectAction> {
+        match self.clone() {
+            EffectActionKind::Destroy { targets_group } => {
+                Box::new(EffectActionDestroy::new(targets_group))
+            }
+            EffectActionKind::Draw {
+                count,
+                targets_group,
+            } => Box::new(EffectActionDraw {
+                count,
+                targets_group,
+            }),
+            EffectActionKind::Damage {
+                amount,
+                targets_group,
+            } => Box::new(EffectActionDamage {
+                amount,
+                targets_group,
+            }),
+            EffectActionKind::GainLife {
+                amount,
+                targets_group,
+            } => Box::new(EffectActionGainLife {
+                amount,
+                targets_group,
+            }),
+            EffectActionKind::ReturnToHand { targets_group } => {
+                Box::new(EffectActionReturnToHand { targets_group })
+            }
+            EffectActionKind::SendToGraveyard { targets_group } => {
+                Box::new(EffectActionSendToGraveyard { targets_group })
+            }
+            EffectActionKind::MoveToSlot {
+                targets_group,
+                slot,
+            } => Box::new(EffectActionMoveToSlot {
+                targets_group,
+                slot,
+            }),
+            EffectActionKind::ModifyStat {
+                targets_group,
+                stat,
+                amount,
+            } => Box::new(EffectActionModifyStat {
+                targets_group,
+                stat,
+                amount,
+            }),
+            EffectActionKind::AddCounter {
+                counter,
+                amount,
+                targets_group,
+            } => Box::new(EffectActionAddCounter {
+                counter,
+                amount,
+                targets_group,
+            }),
+            EffectActionKind::SearchDeck {
+                card,
+                count,
+                targets_group,
+            } => Box::new(EffectActionSearchDeck {
+                card,
+                count,
+                targets_group,
+            }),
+        }
+    }
+}
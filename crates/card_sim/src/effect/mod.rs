@@ -1,19 +1,67 @@
+mod action;
 mod common;
+mod continuous;
+mod cooldown;
+mod cost;
+mod data;
+mod target;
+mod tick;
 mod trigger;
 
+pub use action::*;
 pub use common::*;
+pub use continuous::*;
+pub use cooldown::*;
+pub use cost::*;
+pub use data::*;
+pub use target::*;
+pub use tick::*;
+pub use trigger::*;
 
 use bevy::{
     ecs::system::SystemId,
     prelude::*,
     utils::HashMap,
 };
+use bevy_replicon::{prelude::server_or_singleplayer, server::ServerSet};
+use serde::{Deserialize, Serialize};
 
-pub(crate) fn effect_plugin(app: &mut App) {}
+use crate::{CardDestroyed, CardDrawn, CardSummoned, DamageDealt, StageEntered};
 
+pub(crate) fn effect_plugin(app: &mut App) {
+    app.init_resource::<EffectRegistry>();
+    app.init_resource::<PendingTargets>();
+
+    app.add_systems(
+        Update,
+        (
+            pending_targets_system,
+            continuous_effects_system,
+            effect_tick_system,
+        )
+            .chain()
+            .run_if(server_or_singleplayer)
+            .before(ServerSet::Send),
+    );
+
+    app.observe(cooldown_turn_start_observer);
+    app.observe(continuous_turn_start_observer);
+
+    app.add_game_event::<CardSummoned>();
+    app.add_game_event::<CardDestroyed>();
+    app.add_game_event::<CardDrawn>();
+    app.add_game_event::<StageEntered>();
+    app.add_game_event::<DamageDealt>();
+}
+
+/// An effect system, it receive the data of the effect activation as input
+pub type EffectSystemId = SystemId<EffectActionData>;
+
+/// The effects the game can run, card data reference them by their [`EffectId`]
 #[derive(Resource)]
 pub struct EffectRegistry {
-    effects: HashMap<String, SystemId>,
+    effects: HashMap<EffectId, EffectSystemId>,
+    names: HashMap<String, EffectId>,
 }
 
 impl Default for EffectRegistry {
@@ -26,20 +74,72 @@ impl EffectRegistry {
     pub fn new() -> Self {
         Self {
             effects: HashMap::new(),
+            names: HashMap::new(),
         }
     }
 
-    pub fn register_effect(&mut self, name: String, effect: SystemId) {
-        self.effects.insert(name, effect);
+    pub fn register_effect(&mut self, id: EffectId, name: String, effect: EffectSystemId) {
+        if self.effects.insert(id, effect).is_some() {
+            warn!(
+                "Effect id {} got registered twice, '{}' replace the previous effect",
+                id.0, name
+            );
+        }
+        if let Some(old_id) = self.names.insert(name.clone(), id) {
+            if old_id != id {
+                warn!(
+                    "Effect name '{}' got registered twice, it now refer to the id {} instead of {}",
+                    name, id.0, old_id.0
+                );
+            }
+        }
+    }
+
+    pub fn get_effect(&self, id: &EffectId) -> Option<&EffectSystemId> {
+        self.effects.get(id)
     }
 
-    pub fn get_effect(&self, name: &str) -> Option<&SystemId> {
-        self.effects.get(name)
+    pub fn get_effect_id(&self, name: &str) -> Option<&EffectId> {
+        self.names.get(name)
     }
+
+    pub fn get_effect_by_name(&self, name: &str) -> Option<&EffectSystemId> {
+        self.get_effect_id(name).and_then(|id| self.effects.get(id))
+    }
+}
+
+pub trait EffectAppExt {
+    /// Register a system taking `In<EffectActionData>` as the effect with this id and name
+    fn register_effect<M, S: IntoSystem<EffectActionData, (), M> + 'static>(
+        &mut self,
+        id: EffectId,
+        name: &str,
+        system: S,
+    ) -> &mut Self;
+
+    /// Trigger the effects declaring `EffectTrigger<When<E::Trigger>>` every time the event is triggered
+    fn add_game_event<E: GameEvent>(&mut self) -> &mut Self;
 }
 
-pub fn setup_effects(effect_registry: ResMut<EffectRegistry>) {
-    //    effect_registry.register_effect("destroy".to_string(), SystemId::new());
+impl EffectAppExt for App {
+    fn register_effect<M, S: IntoSystem<EffectActionData, (), M> + 'static>(
+        &mut self,
+        id: EffectId,
+        name: &str,
+        system: S,
+    ) -> &mut Self {
+        let system_id = self.world_mut().register_system(system);
+
+        self.world_mut()
+            .get_resource_or_insert_with(EffectRegistry::default)
+            .register_effect(id, name.to_string(), system_id);
+        self
+    }
+
+    fn add_game_event<E: GameEvent>(&mut self) -> &mut Self {
+        self.observe(game_event_observer::<E>);
+        self
+    }
 }
 
 //TODO comments about invariants effects
@@ -54,12 +154,18 @@ impl Effects {
     pub fn get_effect(&self, index: usize) -> Option<&EffectInstance> {
         self.0.get(index)
     }
-}
 
-#[derive(Component)]
-pub struct EffectTrigger<T: 'static + Send + Sync> {
-    effect_idxs: Vec<usize>,
-    _phantom_data: std::marker::PhantomData<T>,
+    pub fn get_effect_mut(&mut self, index: usize) -> Option<&mut EffectInstance> {
+        self.0.get_mut(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &EffectInstance> {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut EffectInstance> {
+        self.0.iter_mut()
+    }
 }
 
 pub trait Effect {
@@ -67,21 +173,78 @@ pub trait Effect {
     fn get_effect_speed(&self) -> i32;
 }
 
-#[derive(Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct EffectId(pub usize);
 
+/// Speed of the effects that can't respond to a chain, faster effects have a higher speed
+pub const NORMAL_EFFECT_SPEED: i32 = 1;
+
 pub struct EffectInstance {
-    cooldown: usize,
+    cooldown: EffectCooldown,
     effect_id: EffectId,
+    speed: i32,
+    costs: Vec<EffectCostKind>,
+    actions: Vec<EffectActionKind>,
+    continuous: Vec<ContinuousModifierData>,
+    targets_groups: Vec<TargetGroupData>,
 }
 
 impl EffectInstance {
     pub fn new(effect_id: EffectId) -> Self {
         Self {
-            cooldown: 0,
+            cooldown: EffectCooldown::default(),
             effect_id,
+            speed: NORMAL_EFFECT_SPEED,
+            costs: Vec::new(),
+            actions: Vec::new(),
+            continuous: Vec::new(),
+            targets_groups: Vec::new(),
         }
     }
+
+    pub fn from_data(data: &EffectData) -> Self {
+        Self {
+            speed: data.speed,
+            cooldown: EffectCooldown::new(data.cooldown.clone().unwrap_or_default()),
+            costs: data.costs.clone(),
+            actions: data.actions.clone(),
+            continuous: data.continuous.clone(),
+            targets_groups: data.targets_groups.clone(),
+            ..Self::new(data.id)
+        }
+    }
+
+    pub fn get_effect_speed(&self) -> i32 {
+        self.speed
+    }
+
+    pub fn get_cooldown(&self) -> &EffectCooldown {
+        &self.cooldown
+    }
+
+    pub fn get_cooldown_mut(&mut self) -> &mut EffectCooldown {
+        &mut self.cooldown
+    }
+
+    pub fn get_effect_id(&self) -> EffectId {
+        self.effect_id
+    }
+
+    pub fn get_costs(&self) -> &[EffectCostKind] {
+        &self.costs
+    }
+
+    pub fn get_actions(&self) -> &[EffectActionKind] {
+        &self.actions
+    }
+
+    pub fn get_continuous(&self) -> &[ContinuousModifierData] {
+        &self.continuous
+    }
+
+    pub fn get_targets_groups(&self) -> &[TargetGroupData] {
+        &self.targets_groups
+    }
 }
 
 pub trait EffectAction {
@@ -93,44 +256,28 @@ pub struct EffectState {
     effect_data: EffectActionData,
 }
 
-pub struct EffectActionDestroy {
-    targets_group: String,
-}
-
-impl EffectActionDestroy {
-    pub fn new(targets_group: String) -> Self {
-        Self { targets_group }
-    }
-}
-
-impl EffectAction for EffectActionDestroy {
-    fn execute(&self, commands: &mut Commands, data: &EffectActionData) {
-        if let Some(targets) = data.targets.get(&self.targets_group) {
-            for target in targets.iter() {
-                commands.entity(*target).despawn_recursive();
-            }
-        } else {
-            //TODO somehow fix this impossible state by cancelling this effect ig
-            error!("impossible state");
-        }
-    }
-}
-
+/// The input of an effect system
+#[derive(Clone, Debug)]
 pub struct EffectActionData {
-    self_entity: Entity,
-    action_index: u8,
-    agent: Entity,
-    targets: HashMap<String, Vec<Entity>>,
+    pub board: Entity,
+    /// The card owning the effect
+    pub self_entity: Entity,
+    pub action_index: u8,
+    /// The agent controlling the effect
+    pub agent: Entity,
+    pub targets: HashMap<String, Vec<Entity>>,
 }
 
 impl EffectActionData {
     pub fn new(
+        board: Entity,
         self_entity: Entity,
         action_index: u8,
         agent: Entity,
         targets: HashMap<String, Vec<Entity>>,
     ) -> Self {
         Self {
+            board,
             self_entity,
             action_index,
             agent,
@@ -138,8 +285,3 @@ impl EffectActionData {
         }
     }
 }
-
-pub struct TargetGroup {
-    name: String,
-    data_query: QueryState<(), ()>,
-}
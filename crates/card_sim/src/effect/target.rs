@@ -0,0 +1,711 @@
+use std::fmt::{self, Display, Formatter};
+
+use bevy::{ecs::world::Command, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agent_client_id, send_agent_action, send_rejection, AgentOwned, Board, BoardQuery,
+    BoardQueryLoc, BoardQueryResult, BoardRules, ChainError, RejectionReason, RuntimeQueryExt,
+    TagError, TargetAgentAction, TargetGroupData,
+};
+
+use super::{
+    cancel_activation, continue_activation, continue_effect, fail_effect, EffectError,
+    PendingActivation, PreparedEffect,
+};
+
+/// Where the candidates of a target group are looked up, relative to the [`TargetOwner`] of the group
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetZone {
+    Hand,
+    Graveyard,
+    Exile,
+    /// The field and the slots
+    Field,
+    Slot(IVec3),
+    /// The agents themselves, for actions like damage or draw
+    Agents,
+}
+
+/// Whose zones are searched, relative to the agent controlling the effect
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetOwner {
+    #[default]
+    Any,
+    Controller,
+    Opponent,
+}
+
+#[derive(Debug, Clone)]
+pub enum TargetError {
+    BoardNotFound,
+    Tag(TagError),
+    NotEnoughTargets {
+        group: String,
+        found: usize,
+        min: usize,
+    },
+}
+
+impl Display for TargetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetError::BoardNotFound => write!(f, "the board does not exist"),
+            TargetError::Tag(error) => write!(f, "{}", error),
+            TargetError::NotEnoughTargets { group, found, min } => write!(
+                f,
+                "the target group '{}' need at least {} targets but only {} were found",
+                group, min, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TargetError {}
+
+/// A target group of an effect compiled against the world, the tags are turned into a query
+pub struct TargetGroup {
+    name: String,
+    zones: Vec<TargetZone>,
+    owner: TargetOwner,
+    min: usize,
+    max: usize,
+    data_query: Option<QueryState<Entity, ()>>,
+}
+
+impl TargetGroup {
+    pub fn compile(world: &mut World, data: &TargetGroupData) -> Result<Self, TargetError> {
+        let data_query = if data.tags.is_empty() {
+            None
+        } else {
+            Some(
+                world
+                    .query_runtime_tag::<Entity, ()>(&data.tags)
+                    .map_err(TargetError::Tag)?
+                    .build(),
+            )
+        };
+
+        Ok(Self {
+            name: data.name.clone(),
+            zones: data.zones.clone(),
+            owner: data.owner,
+            min: data.min,
+            max: data.max,
+            data_query,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn min(&self) -> usize {
+        self.min
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Every entity of the group zones matching the group tags
+    pub fn candidates(
+        &mut self,
+        world: &World,
+        board_entity: Entity,
+        controller: Entity,
+    ) -> Result<BoardQueryResult, TargetError> {
+        let board = world
+            .get::<Board>(board_entity)
+            .ok_or(TargetError::BoardNotFound)?;
+
+        let agents: Vec<Entity> = board
+            .state
+            .agents
+            .iter()
+            .filter(|agent| match self.owner {
+                TargetOwner::Any => true,
+                TargetOwner::Controller => **agent == controller,
+                TargetOwner::Opponent => **agent != controller,
+            })
+            .copied()
+            .collect();
+
+        let mut locations = Vec::new();
+        let mut candidates = BoardQueryResult::new();
+
+        for zone in self.zones.iter() {
+            match zone {
+                TargetZone::Hand => locations.extend(
+                    agents
+                        .iter()
+                        .map(|agent| BoardQueryLoc::Hand(AgentOwned(*agent))),
+                ),
+                TargetZone::Graveyard => locations.extend(
+                    agents
+                        .iter()
+                        .map(|agent| BoardQueryLoc::Graveyard(AgentOwned(*agent))),
+                ),
+                TargetZone::Exile => locations.extend(
+                    agents
+                        .iter()
+                        .map(|agent| BoardQueryLoc::Exile(AgentOwned(*agent))),
+                ),
+                TargetZone::Field if self.owner == TargetOwner::Any => {
+                    locations.push(BoardQueryLoc::Field(None))
+                }
+                TargetZone::Field => locations.extend(
+                    agents
+                        .iter()
+                        .map(|agent| BoardQueryLoc::Field(Some(AgentOwned(*agent)))),
+                ),
+                TargetZone::Slot(pos) => locations.push(BoardQueryLoc::OnSlot(*pos)),
+                TargetZone::Agents => candidates.extend(
+                    agents
+                        .iter()
+                        .filter(|agent| !board.state.is_defeated(**agent))
+                        .copied(),
+                ),
+            }
+        }
+
+        let mut found = BoardQuery::query(board, &locations);
+        if self.owner != TargetOwner::Any {
+            // The slots are shared between the agents, their occupants are only filtered here
+            found.retain(|entity| {
+                world
+                    .get::<AgentOwned>(*entity)
+                    .map_or(false, |owned| agents.contains(&owned.0))
+            });
+        }
+        candidates.extend(found);
+
+        if let Some(data_query) = &mut self.data_query {
+            candidates.retain(|entity| data_query.get(world, *entity).is_ok());
+        }
+        Ok(candidates)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum TargetSelectionError {
+    /// The request got answered already or never existed
+    UnknownRequest,
+    /// Only the agent controlling the effect can choose its targets
+    NotController,
+    NotACandidate,
+    Duplicate,
+    Count {
+        selected: usize,
+        min: usize,
+        max: usize,
+    },
+    /// Only the costs of an activation can be cancelled, not the targets of a resolving effect
+    NotCancellable,
+}
+
+impl Display for TargetSelectionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetSelectionError::UnknownRequest => {
+                write!(f, "nothing is waiting on this request")
+            }
+            TargetSelectionError::NotController => {
+                write!(f, "the agent does not control the effect")
+            }
+            TargetSelectionError::NotACandidate => write!(f, "a target is not a valid candidate"),
+            TargetSelectionError::Duplicate => write!(f, "a target got selected twice"),
+            TargetSelectionError::Count { selected, min, max } => write!(
+                f,
+                "{} targets got selected but between {} and {} are needed",
+                selected, min, max
+            ),
+            TargetSelectionError::NotCancellable => {
+                write!(f, "the selection can't be cancelled")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TargetSelectionError {}
+
+/// What is waiting on a selection of the agent
+pub(crate) enum PendingSelection {
+    /// An effect choosing the targets of a group
+    Effect(PreparedEffect),
+    /// An activation choosing the cards of a discard or tribute cost, it can be cancelled
+    Activation(PendingActivation),
+}
+
+impl PendingSelection {
+    fn board(&self) -> Entity {
+        match self {
+            PendingSelection::Effect(effect) => effect.board(),
+            PendingSelection::Activation(activation) => activation.board(),
+        }
+    }
+
+    fn agent(&self) -> Entity {
+        match self {
+            PendingSelection::Effect(effect) => effect.agent(),
+            PendingSelection::Activation(activation) => activation.agent(),
+        }
+    }
+}
+
+/// A selection waiting on the agent to choose among the candidates
+pub(crate) struct PendingTarget {
+    waiting: PendingSelection,
+    candidates: Vec<Entity>,
+    min: usize,
+    max: usize,
+    /// The first candidates are picked for the agent when it runs out, same duration as the priority timeout
+    timer: Timer,
+}
+
+impl PendingTarget {
+    fn validate(&self, agent: Entity, targets: &[Entity]) -> Result<(), TargetSelectionError> {
+        if self.waiting.agent() != agent {
+            return Err(TargetSelectionError::NotController);
+        }
+        if targets.len() < self.min || targets.len() > self.max {
+            return Err(TargetSelectionError::Count {
+                selected: targets.len(),
+                min: self.min,
+                max: self.max,
+            });
+        }
+        for (index, target) in targets.iter().enumerate() {
+            if !self.candidates.contains(target) {
+                return Err(TargetSelectionError::NotACandidate);
+            }
+            if targets[..index].contains(target) {
+                return Err(TargetSelectionError::Duplicate);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The effects and activations waiting on a [`TargetAgentAction`], by request
+#[derive(Resource, Default)]
+pub struct PendingTargets {
+    next_request: u32,
+    pending: HashMap<u32, PendingTarget>,
+}
+
+impl PendingTargets {
+    pub fn is_pending(&self, request: u32) -> bool {
+        self.pending.contains_key(&request)
+    }
+
+    /// The agent is paying the costs of an activation on the board, it can't act on the chain until it is done
+    pub fn has_pending_activation(&self, board: Entity, agent: Entity) -> bool {
+        self.pending.values().any(|pending| {
+            matches!(pending.waiting, PendingSelection::Activation(_))
+                && pending.waiting.board() == board
+                && pending.waiting.agent() == agent
+        })
+    }
+}
+
+/// Ask the controller of the effect to choose the targets of the group among the candidates
+/// Returns the effect back when the controller has no client to ask
+pub(crate) fn request_targets(
+    world: &mut World,
+    effect: PreparedEffect,
+    group: &TargetGroup,
+    candidates: Vec<Entity>,
+) -> Option<PreparedEffect> {
+    if agent_client_id(world, effect.agent()).is_none() {
+        return Some(effect);
+    }
+
+    request_selection(
+        world,
+        PendingSelection::Effect(effect),
+        group.name(),
+        candidates,
+        group.min(),
+        group.max(),
+    );
+    None
+}
+
+/// Send a [`TargetAgentAction`] to the agent of the selection and keep it waiting until it answers
+pub(crate) fn request_selection(
+    world: &mut World,
+    waiting: PendingSelection,
+    group: &str,
+    candidates: Vec<Entity>,
+    min: usize,
+    max: usize,
+) {
+    let mut pending_targets = world.get_resource_or_insert_with(PendingTargets::default);
+    let request = pending_targets.next_request;
+    pending_targets.next_request = pending_targets.next_request.wrapping_add(1);
+
+    let board = waiting.board();
+    let agent = waiting.agent();
+    let action = TargetAgentAction::new(request, group.to_string(), candidates.clone(), min, max);
+
+    let timeout = world
+        .get::<Board>(board)
+        .map_or(BoardRules::default().priority_timeout, |board| {
+            board.state.rules.priority_timeout
+        });
+    world.resource_mut::<PendingTargets>().pending.insert(
+        request,
+        PendingTarget {
+            waiting,
+            candidates,
+            min,
+            max,
+            timer: Timer::from_seconds(timeout, TimerMode::Once),
+        },
+    );
+
+    send_agent_action(world, board, agent, action);
+}
+
+/// Resume the effect or activation waiting on the request with the targets chosen by the agent, server side only
+/// An invalid selection is rejected and the request keeps waiting for a valid one
+pub struct SelectTargetsCommand {
+    pub board: Entity,
+    pub agent: Entity,
+    pub request: u32,
+    pub targets: Vec<Entity>,
+}
+
+impl Command for SelectTargetsCommand {
+    fn apply(self, world: &mut World) {
+        let result = match world
+            .get_resource::<PendingTargets>()
+            .and_then(|pending_targets| pending_targets.pending.get(&self.request))
+        {
+            Some(pending) if pending.waiting.board() == self.board => {
+                pending.validate(self.agent, &self.targets)
+            }
+            _ => Err(TargetSelectionError::UnknownRequest),
+        };
+
+        if let Err(error) = result {
+            warn!(
+                "SelectTargetsCommand: agent {:?} sent invalid targets for the request {}: {}",
+                self.agent, self.request, error
+            );
+            send_rejection(
+                world,
+                self.board,
+                self.agent,
+                RejectionReason::Targets(error),
+            );
+            return;
+        }
+
+        let Some(PendingTarget { waiting, .. }) = world
+            .resource_mut::<PendingTargets>()
+            .pending
+            .remove(&self.request)
+        else {
+            return;
+        };
+
+        resume_selection(world, waiting, self.targets);
+    }
+}
+
+/// Resume what was waiting on the selection with the chosen targets
+fn resume_selection(world: &mut World, waiting: PendingSelection, targets: Vec<Entity>) {
+    match waiting {
+        PendingSelection::Effect(mut effect) => {
+            let (board, card, effect_index) =
+                (effect.board(), effect.card(), effect.effect_index());
+            let result = refresh_targets(world, &effect, targets).and_then(|targets| {
+                effect.set_next_targets(targets);
+                continue_effect(world, effect)
+            });
+
+            if let Err(error) = result {
+                fail_effect(world, board, card, effect_index, error);
+            }
+        }
+        // The cards are checked again when they are paid
+        PendingSelection::Activation(mut activation) => {
+            match activation.pay_cards(world, targets) {
+                Ok(()) => continue_activation(world, activation),
+                Err(error) => cancel_activation(world, activation, Some(ChainError::Cost(error))),
+            }
+        }
+    }
+}
+
+/// Keep the targets that are still candidates of the group, the board may have changed while the agent was choosing
+fn refresh_targets(
+    world: &mut World,
+    effect: &PreparedEffect,
+    mut targets: Vec<Entity>,
+) -> Result<Vec<Entity>, EffectError> {
+    let Some(data) = effect.next_group() else {
+        return Ok(targets);
+    };
+    let mut group = TargetGroup::compile(world, data).map_err(EffectError::Targets)?;
+    let candidates = group
+        .candidates(world, effect.board(), effect.agent())
+        .map_err(EffectError::Targets)?;
+
+    targets.retain(|target| candidates.contains(target));
+    targets.truncate(group.max());
+
+    if targets.len() < group.min() {
+        return Err(EffectError::Targets(TargetError::NotEnoughTargets {
+            group: group.name().to_string(),
+            found: targets.len(),
+            min: group.min(),
+        }));
+    }
+    Ok(targets)
+}
+
+/// Pick the first candidates of the selections the agents did not answer in time
+/// Selections of finished boards or of agents without a client anymore are dropped, the paid costs are given back
+pub(crate) fn pending_targets_system(world: &mut World) {
+    let delta = world.resource::<Time>().delta();
+    let mut pending_targets = world.resource_mut::<PendingTargets>();
+    if pending_targets.pending.is_empty() {
+        return;
+    }
+
+    for pending in pending_targets.pending.values_mut() {
+        pending.timer.tick(delta);
+    }
+
+    let requests: Vec<(u32, bool)> = pending_targets
+        .pending
+        .iter()
+        .map(|(request, pending)| (*request, pending.timer.finished()))
+        .collect();
+
+    for (request, expired) in requests {
+        let (board, agent) = {
+            let pending = &world.resource::<PendingTargets>().pending[&request];
+            (pending.waiting.board(), pending.waiting.agent())
+        };
+        let dropped = world
+            .get::<Board>(board)
+            .map_or(true, |board| board.state.is_finished())
+            || agent_client_id(world, agent).is_none();
+
+        if !dropped && !expired {
+            continue;
+        }
+
+        let Some(PendingTarget {
+            waiting,
+            candidates,
+            max,
+            ..
+        }) = world
+            .resource_mut::<PendingTargets>()
+            .pending
+            .remove(&request)
+        else {
+            continue;
+        };
+
+        if dropped {
+            info!(
+                "Dropping the target request {} of the agent {:?}, the board {:?} finished or the agent left",
+                request, agent, board
+            );
+            if let PendingSelection::Activation(activation) = waiting {
+                cancel_activation(world, activation, None);
+            }
+            continue;
+        }
+
+        info!(
+            "Agent {:?} did not answer the target request {} in time, picking the first candidates",
+            agent, request
+        );
+        // The effect targets are filtered against the fresh candidates when resuming
+        let targets = match &waiting {
+            PendingSelection::Effect(_) => candidates,
+            PendingSelection::Activation(activation) => activation.candidates(world),
+        };
+        resume_selection(world, waiting, targets.into_iter().take(max).collect());
+    }
+}
+
+/// Cancel the activation waiting on the request and give back its paid costs, server side only
+/// Effects already on the chain can't be cancelled
+pub struct CancelSelectionCommand {
+    pub board: Entity,
+    pub agent: Entity,
+    pub request: u32,
+}
+
+impl Command for CancelSelectionCommand {
+    fn apply(self, world: &mut World) {
+        let result = match world
+            .get_resource::<PendingTargets>()
+            .and_then(|pending_targets| pending_targets.pending.get(&self.request))
+        {
+            Some(pending) if pending.waiting.board() == self.board => {
+                if pending.waiting.agent() != self.agent {
+                    Err(TargetSelectionError::NotController)
+                } else if matches!(pending.waiting, PendingSelection::Effect(_)) {
+                    Err(TargetSelectionError::NotCancellable)
+                } else {
+                    Ok(())
+                }
+            }
+            _ => Err(TargetSelectionError::UnknownRequest),
+        };
+
+        if let Err(error) = result {
+            warn!(
+                "CancelSelectionCommand: agent {:?} could not cancel the request {}: {}",
+                self.agent, self.request, error
+            );
+            send_rejection(
+                world,
+                self.board,
+                self.agent,
+                RejectionReason::Targets(error),
+            );
+            return;
+        }
+
+        if let Some(PendingTarget {
+            waiting: PendingSelection::Activation(activation),
+            ..
+        }) = world
+            .resource_mut::<PendingTargets>()
+            .pending
+            .remove(&self.request)
+        {
+            cancel_activation(world, activation, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoardSlot, Card, OnBoard, OnField, OnHand, OnSlot, TagRegistry};
+
+    #[derive(Component)]
+    struct Flying;
+
+    fn setup() -> (World, Entity, Entity, Entity) {
+        let mut world = World::new();
+        let a = world.spawn_empty().id();
+        let b = world.spawn_empty().id();
+        let board = world.spawn(Board::with_seed(vec![a, b], 0)).id();
+
+        let flying = world.init_component::<Flying>();
+        let mut tags = TagRegistry::new();
+        tags.register("flying".to_string(), flying);
+        world.insert_resource(tags);
+
+        (world, board, a, b)
+    }
+
+    fn group(
+        world: &mut World,
+        zones: Vec<TargetZone>,
+        owner: TargetOwner,
+        tags: &str,
+    ) -> TargetGroup {
+        let mut data: TargetGroupData =
+            serde_json::from_str(&format!(r#"{{ "name": "targets", "tags": {} }}"#, tags)).unwrap();
+        data.zones = zones;
+        data.owner = owner;
+        TargetGroup::compile(world, &data).unwrap()
+    }
+
+    fn spawn_on_field(world: &mut World, board: Entity, agent: Entity) -> Entity {
+        world
+            .spawn((Card, AgentOwned(agent), OnBoard(board), OnField))
+            .id()
+    }
+
+    #[test]
+    fn opponent_field_only_has_the_opponent_cards() {
+        let (mut world, board, a, b) = setup();
+        spawn_on_field(&mut world, board, a);
+        let theirs = spawn_on_field(&mut world, board, b);
+        world.spawn((Card, AgentOwned(b), OnBoard(board), OnHand));
+
+        let mut group = group(
+            &mut world,
+            vec![TargetZone::Field],
+            TargetOwner::Opponent,
+            "[]",
+        );
+        let candidates = group.candidates(&world, board, a).unwrap();
+        assert_eq!(candidates.as_slice(), &[theirs]);
+    }
+
+    #[test]
+    fn slot_occupants_are_filtered_by_owner() {
+        let (mut world, board, a, b) = setup();
+        let slot = world
+            .spawn((BoardSlot(IVec3::ZERO, None), OnBoard(board)))
+            .id();
+        let card = world
+            .spawn((Card, AgentOwned(b), OnBoard(board), OnSlot(slot)))
+            .id();
+        let zones = vec![TargetZone::Slot(IVec3::ZERO)];
+
+        let mut controller = group(&mut world, zones.clone(), TargetOwner::Controller, "[]");
+        assert!(controller.candidates(&world, board, a).unwrap().is_empty());
+
+        let mut opponent = group(&mut world, zones, TargetOwner::Opponent, "[]");
+        assert_eq!(
+            opponent.candidates(&world, board, a).unwrap().as_slice(),
+            &[card]
+        );
+    }
+
+    #[test]
+    fn defeated_agents_are_not_candidates() {
+        let (mut world, board, a, b) = setup();
+
+        let mut group = group(&mut world, vec![TargetZone::Agents], TargetOwner::Any, "[]");
+        assert_eq!(
+            group.candidates(&world, board, a).unwrap().as_slice(),
+            &[a, b]
+        );
+
+        world
+            .get_mut::<Board>(board)
+            .unwrap()
+            .state
+            .defeated
+            .push(b);
+        assert_eq!(group.candidates(&world, board, a).unwrap().as_slice(), &[a]);
+    }
+
+    #[test]
+    fn tags_remove_the_candidates_without_them() {
+        let (mut world, board, a, b) = setup();
+        spawn_on_field(&mut world, board, b);
+        let flying = spawn_on_field(&mut world, board, b);
+        world.entity_mut(flying).insert(Flying);
+
+        let mut group = group(
+            &mut world,
+            vec![TargetZone::Field],
+            TargetOwner::Any,
+            r#"[{ "with": ["flying"] }]"#,
+        );
+        assert_eq!(
+            group.candidates(&world, board, a).unwrap().as_slice(),
+            &[flying]
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt::{self, Display, Formatter};
+
+use bevy::{ecs::world::CommandQueue, prelude::*, utils::HashMap};
+
+use crate::{AgentOwned, Board};
+
+use super::{
+    request_targets, EffectActionData, EffectActionKind, EffectId, EffectRegistry, EffectSystemId,
+    Effects, TargetError, TargetGroup, TargetGroupData,
+};
+
+#[derive(Debug, Clone)]
+pub enum EffectError {
+    CardNotFound,
+    /// The card has no effect at this index, the card data probably changed since the effect got triggered
+    UnknownEffectIndex(usize),
+    /// No effect system got registered with this id
+    UnknownEffectId(EffectId),
+    Targets(TargetError),
+    /// The card has no owner and the board has no turn agent to control it
+    NoController,
+}
+
+impl Display for EffectError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EffectError::CardNotFound => write!(f, "the card does not exist anymore"),
+            EffectError::UnknownEffectIndex(index) => {
+                write!(f, "the card has no effect at the index {}", index)
+            }
+            EffectError::UnknownEffectId(id) => {
+                write!(f, "no effect is registered with the id {}", id.0)
+            }
+            EffectError::Targets(error) => {
+                write!(f, "the targets could not be resolved: {}", error)
+            }
+            EffectError::NoController => write!(f, "nobody controls the card"),
+        }
+    }
+}
+
+impl std::error::Error for EffectError {}
+
+/// Triggered on the server when a triggered effect could not run, the effect is skipped and the board keep going
+#[derive(Event, Clone, Debug)]
+pub struct EffectFailed {
+    pub board: Entity,
+    pub card: Entity,
+    pub effect_index: usize,
+    pub error: EffectError,
+}
+
+/// Everything needed to run an effect, the actions run first then the registered system
+pub(crate) struct PreparedEffect {
+    effect_index: usize,
+    system_id: Option<EffectSystemId>,
+    actions: Vec<EffectActionKind>,
+    targets_groups: Vec<TargetGroupData>,
+    /// The groups before this one already have their targets in the data
+    next_group: usize,
+    data: EffectActionData,
+}
+
+impl PreparedEffect {
+    pub(crate) fn board(&self) -> Entity {
+        self.data.board
+    }
+
+    pub(crate) fn card(&self) -> Entity {
+        self.data.self_entity
+    }
+
+    pub(crate) fn effect_index(&self) -> usize {
+        self.effect_index
+    }
+
+    pub(crate) fn agent(&self) -> Entity {
+        self.data.agent
+    }
+
+    /// The group waiting on its targets, if any
+    pub(crate) fn next_group(&self) -> Option<&TargetGroupData> {
+        self.targets_groups.get(self.next_group)
+    }
+
+    /// Set the targets of the next group and move to the one after
+    pub(crate) fn set_next_targets(&mut self, targets: Vec<Entity>) {
+        if let Some(group) = self.targets_groups.get(self.next_group) {
+            self.data.targets.insert(group.name.clone(), targets);
+            self.next_group += 1;
+        }
+    }
+}
+
+/// Find the system and actions of the effect and build its input
+fn prepare_effect(
+    world: &World,
+    board_entity: Entity,
+    card: Entity,
+    effect_index: usize,
+) -> Result<PreparedEffect, EffectError> {
+    let entity = world.get_entity(card).ok_or(EffectError::CardNotFound)?;
+    let effect = entity
+        .get::<Effects>()
+        .and_then(|effects| effects.get_effect(effect_index))
+        .ok_or(EffectError::UnknownEffectIndex(effect_index))?;
+    let system_id = world
+        .resource::<EffectRegistry>()
+        .get_effect(&effect.get_effect_id())
+        .copied();
+
+    // Effects only made of built-in actions don't need a registered system
+    if system_id.is_none() && effect.get_actions().is_empty() {
+        return Err(EffectError::UnknownEffectId(effect.get_effect_id()));
+    }
+
+    // Cards without owner are controlled by the turn agent
+    let agent = entity.get::<AgentOwned>().map(|owned| owned.0).or_else(|| {
+        world
+            .get::<Board>(board_entity)
+            .and_then(|board| *board.state.get_current_turn_agent())
+    });
+    let agent = agent.ok_or(EffectError::NoController)?;
+
+    Ok(PreparedEffect {
+        effect_index,
+        system_id,
+        actions: effect.get_actions().to_vec(),
+        targets_groups: effect.get_targets_groups().to_vec(),
+        next_group: 0,
+        data: EffectActionData::new(board_entity, card, 0, agent, HashMap::new()),
+    })
+}
+
+/// Resolve the remaining target groups then run the effect
+/// When the controller has a choice to make the effect is put on hold until it answers, see [`request_targets`]
+pub(crate) fn continue_effect(
+    world: &mut World,
+    mut effect: PreparedEffect,
+) -> Result<(), EffectError> {
+    while let Some(data) = effect.targets_groups.get(effect.next_group) {
+        let mut group = TargetGroup::compile(world, data).map_err(EffectError::Targets)?;
+        let candidates = group
+            .candidates(world, effect.data.board, effect.data.agent)
+            .map_err(EffectError::Targets)?;
+
+        if candidates.len() < group.min() {
+            return Err(EffectError::Targets(TargetError::NotEnoughTargets {
+                group: group.name().to_string(),
+                found: candidates.len(),
+                min: group.min(),
+            }));
+        }
+
+        // There is no choice to make when every candidate is needed
+        if candidates.len() > group.min() {
+            match request_targets(world, effect, &group, candidates.to_vec()) {
+                Some(returned) => effect = returned,
+                None => return Ok(()),
+            }
+        }
+
+        // Agents without a client to ask get the first candidates
+        effect.set_next_targets(candidates.into_iter().take(group.max()).collect());
+    }
+
+    run_effect(world, effect);
+    Ok(())
+}
+
+fn run_effect(world: &mut World, effect: PreparedEffect) {
+    let PreparedEffect {
+        system_id,
+        actions,
+        mut data,
+        ..
+    } = effect;
+
+    if !actions.is_empty() {
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, world);
+
+        for (action_index, action) in actions.iter().enumerate() {
+            data.action_index = action_index as u8;
+            action.create_action().execute(&mut commands, &data);
+        }
+        queue.apply(world);
+    }
+
+    if let Some(system_id) = system_id {
+        let card = data.self_entity;
+
+        if let Err(e) = world.run_system_with_input(system_id, data) {
+            error!("Effect system of the card {:?} could not run: {}", card, e);
+        }
+    }
+}
+
+pub(crate) fn fail_effect(
+    world: &mut World,
+    board: Entity,
+    card: Entity,
+    effect_index: usize,
+    error: EffectError,
+) {
+    error!(
+        "Effect {} of the card {:?} on the board {:?} got skipped: {}",
+        effect_index, card, board, error
+    );
+    world.trigger(EffectFailed {
+        board,
+        card,
+        effect_index,
+        error,
+    });
+}
+
+/// Run the effects triggered on every board since the last tick, in the order they got triggered
+pub(crate) fn effect_tick_system(world: &mut World) {
+    let boards: Vec<Entity> = world
+        .query_filtered::<Entity, With<Board>>()
+        .iter(world)
+        .collect();
+
+    for board_entity in boards {
+        // Avoid touching the board when there is nothing to run so it doesn't get replicated for nothing
+        if world
+            .get::<Board>(board_entity)
+            .map_or(true, |board| board.state.tick_triggers.is_empty())
+        {
+            continue;
+        }
+
+        let triggers = std::mem::take(
+            &mut world
+                .get_mut::<Board>(board_entity)
+                .unwrap()
+                .state
+                .tick_triggers,
+        );
+
+        for (card, effect_index) in triggers {
+            let result = prepare_effect(world, board_entity, card, effect_index)
+                .and_then(|effect| continue_effect(world, effect));
+
+            if let Err(error) = result {
+                fail_effect(world, board_entity, card, effect_index, error);
+            }
+        }
+    }
+}
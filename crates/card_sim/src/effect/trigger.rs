@@ -2,19 +2,202 @@ use bevy::{
     ecs::{system::SystemState, world::Command},
     prelude::*,
 };
+use serde::{Deserialize, Serialize};
 
-use crate::Board;
+use crate::{
+    AgentOwned, Board, BoardQuery, BoardQueryLoc, CardDestroyed, CardDrawn, CardSummoned,
+    ChainLink, DamageDealt, StageEntered,
+};
+
+use super::{
+    check_cooldown, record_activation, Damage, Destroy, Draw, EffectTriggerKind, Effects,
+    StageEnter, Summon, When,
+};
 
-use super::Effects;
+/// Who has to cause the event for the effect to trigger, compared to the card owning the effect
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerFilter {
+    #[default]
+    Any,
+    /// "When this card", the card is the subject of the event
+    This,
+    /// "When you", the subject is the agent controlling the card or one of its cards
+    Controller,
+    /// "When an opponent", the subject is another agent or one of its cards
+    Opponent,
+}
 
+impl TriggerFilter {
+    /// The controller of a card without owner is the turn agent, same as when its effects run
+    pub fn matches(
+        &self,
+        card: Entity,
+        controller: Option<Entity>,
+        subject_card: Option<Entity>,
+        subject_agent: Option<Entity>,
+    ) -> bool {
+        match self {
+            TriggerFilter::Any => true,
+            TriggerFilter::This => subject_card == Some(card),
+            TriggerFilter::Controller => {
+                controller.is_some() && subject_agent.is_some() && controller == subject_agent
+            }
+            TriggerFilter::Opponent => {
+                controller.is_some() && subject_agent.is_some() && controller != subject_agent
+            }
+        }
+    }
+}
+
+/// The effects of the card triggered by the event `T`, usually a [`When`] of a game event marker
 #[derive(Component)]
 pub struct EffectTrigger<T: Event> {
-    effect_idxs: Vec<usize>,
+    effects: Vec<(usize, TriggerFilter)>,
     _phantom_data: std::marker::PhantomData<T>,
 }
 
+impl<T: Event> EffectTrigger<T> {
+    pub fn new(effects: Vec<(usize, TriggerFilter)>) -> Self {
+        Self {
+            effects,
+            _phantom_data: std::marker::PhantomData,
+        }
+    }
+
+    /// The index of the effects with the filter their subject must pass
+    pub fn get_effects(&self) -> &[(usize, TriggerFilter)] {
+        &self.effects
+    }
+}
+
+impl EffectTriggerKind {
+    /// Insert the [`EffectTrigger`] matching this kind on the card
+    pub fn insert_trigger(&self, card: &mut EntityWorldMut, effects: Vec<(usize, TriggerFilter)>) {
+        match self {
+            EffectTriggerKind::Summon => card.insert(EffectTrigger::<When<Summon>>::new(effects)),
+            EffectTriggerKind::Destroy => card.insert(EffectTrigger::<When<Destroy>>::new(effects)),
+            EffectTriggerKind::Draw => card.insert(EffectTrigger::<When<Draw>>::new(effects)),
+            EffectTriggerKind::StageEnter => {
+                card.insert(EffectTrigger::<When<StageEnter>>::new(effects))
+            }
+            EffectTriggerKind::Damage => card.insert(EffectTrigger::<When<Damage>>::new(effects)),
+        };
+    }
+
+    /// Remove every [`EffectTrigger`] of the card, whatever its kind
+    pub fn remove_triggers(card: &mut EntityWorldMut) {
+        card.remove::<(
+            EffectTrigger<When<Summon>>,
+            EffectTrigger<When<Destroy>>,
+            EffectTrigger<When<Draw>>,
+            EffectTrigger<When<StageEnter>>,
+            EffectTrigger<When<Damage>>,
+        )>();
+    }
+}
+
+/// A server side event of the simulation, the effects with an `EffectTrigger<When<Self::Trigger>>` fire when it happen
+/// Registered with [`crate::EffectAppExt::add_game_event`]
+pub trait GameEvent: Event {
+    type Trigger: Send + Sync + 'static;
+
+    fn board(&self) -> Entity;
+
+    /// The card the event happened to, if any
+    fn subject_card(&self) -> Option<Entity> {
+        None
+    }
+
+    /// The agent that caused or received the event, the owner of the subject card is used when none
+    fn subject_agent(&self) -> Option<Entity> {
+        None
+    }
+}
+
+impl GameEvent for CardSummoned {
+    type Trigger = Summon;
+
+    fn board(&self) -> Entity {
+        self.board
+    }
+
+    fn subject_card(&self) -> Option<Entity> {
+        Some(self.card)
+    }
+
+    fn subject_agent(&self) -> Option<Entity> {
+        Some(self.agent)
+    }
+}
+
+impl GameEvent for CardDestroyed {
+    type Trigger = Destroy;
+
+    fn board(&self) -> Entity {
+        self.board
+    }
+
+    fn subject_card(&self) -> Option<Entity> {
+        Some(self.card)
+    }
+}
+
+impl GameEvent for CardDrawn {
+    type Trigger = Draw;
+
+    fn board(&self) -> Entity {
+        self.board
+    }
+
+    fn subject_card(&self) -> Option<Entity> {
+        Some(self.card)
+    }
+
+    fn subject_agent(&self) -> Option<Entity> {
+        Some(self.agent)
+    }
+}
+
+impl GameEvent for StageEntered {
+    type Trigger = StageEnter;
+
+    fn board(&self) -> Entity {
+        self.board
+    }
+
+    fn subject_agent(&self) -> Option<Entity> {
+        Some(self.agent)
+    }
+}
+
+impl GameEvent for DamageDealt {
+    type Trigger = Damage;
+
+    fn board(&self) -> Entity {
+        self.board
+    }
+
+    fn subject_agent(&self) -> Option<Entity> {
+        Some(self.agent)
+    }
+}
+
+pub(crate) fn game_event_observer<E: GameEvent>(trigger: Trigger<E>, mut commands: Commands) {
+    let event = trigger.event();
+
+    commands.add(
+        TriggerEffectsCommand::<When<E::Trigger>>::new(event.board())
+            .with_subject(event.subject_card(), event.subject_agent()),
+    );
+}
+
+/// Queue the effects of the board triggered by `T` whose filter accept the subject, they run on the next effect tick
+/// A triggered effect counts as an activation for the cooldowns, the effects still on cooldown don't trigger
 pub struct TriggerEffectsCommand<T: 'static + Send + Sync> {
     board: Entity,
+    subject_card: Option<Entity>,
+    subject_agent: Option<Entity>,
     phantom: std::marker::PhantomData<T>,
 }
 
@@ -22,30 +205,72 @@ impl<T: 'static + Send + Sync> TriggerEffectsCommand<T> {
     pub fn new(board: Entity) -> Self {
         Self {
             board,
+            subject_card: None,
+            subject_agent: None,
             phantom: std::marker::PhantomData,
         }
     }
+
+    /// Without subject only the effects with [`TriggerFilter::Any`] trigger
+    pub fn with_subject(mut self, card: Option<Entity>, agent: Option<Entity>) -> Self {
+        self.subject_card = card;
+        self.subject_agent = agent;
+        self
+    }
 }
 
 impl<T: Event + 'static + Send + Sync> Command for TriggerEffectsCommand<T> {
     fn apply(self, world: &mut World) {
-        let mut system_state =
-            SystemState::<(Query<(&EffectTrigger<T>, &Effects)>, Query<&mut Board>)>::new(world);
-        let (triggers, mut boards) = system_state.get_mut(world);
-
-        if let Ok(mut board) = boards.get_mut(self.board) {
-            for entity in board
-                .cache
-                .get_entities()
-                .iter()
-                .cloned()
-                .collect::<Vec<_>>()
-            {
+        let mut system_state = SystemState::<(
+            Query<(&EffectTrigger<T>, &Effects)>,
+            Query<&AgentOwned>,
+            Query<&Board>,
+        )>::new(world);
+        let (triggers, owners, boards) = system_state.get(world);
+        let mut triggered = Vec::new();
+
+        if let Ok(board) = boards.get(self.board) {
+            let subject_agent = self.subject_agent.or_else(|| {
+                self.subject_card
+                    .and_then(|card| owners.get(card).ok())
+                    .map(|owned| owned.0)
+            });
+            let turn_agent = *board.state.get_current_turn_agent();
+
+            // Only the cards on the field and the slots react to the events
+            // The subject card itself can be elsewhere (drawn, destroyed) and still trigger its "when this card" effects
+            let mut listeners: Vec<(Entity, bool)> =
+                BoardQuery::query(board, &[BoardQueryLoc::Field(None)])
+                    .into_iter()
+                    .map(|entity| (entity, true))
+                    .collect();
+            if let Some(card) = self.subject_card.filter(|card| {
+                board.cache.get_entities().contains(card)
+                    && !listeners.iter().any(|(entity, _)| entity == card)
+            }) {
+                listeners.push((card, false));
+            }
+
+            for (entity, on_field) in listeners {
                 if let Ok((trigger, effects)) = triggers.get(entity) {
+                    let controller = owners.get(entity).map(|owned| owned.0).ok().or(turn_agent);
+
                     //TODO invariants ? make the match a draw or cancel the effect
-                    for idx in trigger.effect_idxs.iter() {
+                    for (idx, filter) in trigger.effects.iter() {
+                        if !on_field && *filter != TriggerFilter::This {
+                            continue;
+                        }
+                        if !filter.matches(entity, controller, self.subject_card, subject_agent) {
+                            continue;
+                        }
                         if let Some(effect) = effects.get_effect(*idx) {
-                            board.trigger_effect(entity, *idx);
+                            let link = controller.map(|agent| ChainLink {
+                                card: entity,
+                                effect_index: *idx,
+                                agent,
+                                speed: effect.get_effect_speed(),
+                            });
+                            triggered.push((entity, *idx, link));
                         } else {
                             warn!("EffectTriggerCommand: effects indexs are broken, this should not be possible, skipping");
                         }
@@ -55,5 +280,76 @@ impl<T: Event + 'static + Send + Sync> Command for TriggerEffectsCommand<T> {
         } else {
             warn!("EffectTriggerCommand's entity has no board, skipping");
         }
+
+        for (entity, idx, link) in triggered {
+            // Without controller the effect can't run, it fails on the next tick
+            if let Some(link) = link {
+                let Some(board) = world.get::<Board>(self.board) else {
+                    return;
+                };
+                if let Err(e) = check_cooldown(world, board, &link) {
+                    info!(
+                        "Effect {} of {:?} did not trigger: {}",
+                        link.effect_index, link.card, e
+                    );
+                    continue;
+                }
+                record_activation(world, self.board, &link);
+            }
+
+            if let Some(mut board) = world.get_mut::<Board>(self.board) {
+                board.trigger_effect(entity, idx);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entities() -> (Entity, Entity, Entity, Entity) {
+        (
+            Entity::from_raw(1),
+            Entity::from_raw(2),
+            Entity::from_raw(3),
+            Entity::from_raw(4),
+        )
+    }
+
+    #[test]
+    fn any_always_matches() {
+        let (card, _, _, _) = entities();
+
+        assert!(TriggerFilter::Any.matches(card, None, None, None));
+    }
+
+    #[test]
+    fn this_needs_the_card_as_subject() {
+        let (card, other_card, controller, _) = entities();
+
+        assert!(TriggerFilter::This.matches(card, Some(controller), Some(card), None));
+        assert!(!TriggerFilter::This.matches(card, Some(controller), Some(other_card), None));
+        assert!(!TriggerFilter::This.matches(card, Some(controller), None, Some(controller)));
+    }
+
+    #[test]
+    fn controller_and_opponent_compare_the_subject_agent() {
+        let (card, _, controller, opponent) = entities();
+
+        assert!(TriggerFilter::Controller.matches(card, Some(controller), None, Some(controller)));
+        assert!(!TriggerFilter::Controller.matches(card, Some(controller), None, Some(opponent)));
+        assert!(TriggerFilter::Opponent.matches(card, Some(controller), None, Some(opponent)));
+        assert!(!TriggerFilter::Opponent.matches(card, Some(controller), None, Some(controller)));
+    }
+
+    #[test]
+    fn controller_and_opponent_need_both_agents() {
+        let (card, _, controller, _) = entities();
+
+        assert!(!TriggerFilter::Controller.matches(card, None, None, Some(controller)));
+        assert!(!TriggerFilter::Controller.matches(card, Some(controller), None, None));
+        assert!(!TriggerFilter::Opponent.matches(card, None, None, Some(controller)));
+        assert!(!TriggerFilter::Opponent.matches(card, Some(controller), None, None));
     }
 }
@@ -16,7 +16,7 @@ pub struct CardSimPlugin;
 impl Plugin for CardSimPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.register_type::<AgentManager>();
-        app.add_plugins((board_plugin, card_plugin));
+        app.add_plugins((query_plugin, board_plugin, card_plugin, effect_plugin));
     }
 }
 
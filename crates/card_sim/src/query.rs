@@ -1,28 +1,157 @@
+use std::fmt::{self, Display, Formatter};
+
 use bevy::prelude::*;
 use bevy::{
     ecs::{
         component::ComponentId,
         query::{QueryData, QueryFilter},
     },
+    reflect::FromType,
     utils::HashMap,
 };
 use serde::{Deserialize, Serialize};
 
-#[derive(Resource)]
+pub(crate) fn query_plugin(app: &mut App) {
+    app.init_resource::<TagRegistry>();
+
+    app.add_systems(PreStartup, register_reflected_tags);
+}
+
+/// The components card data can reference by name in their queries
+#[derive(Resource, Default)]
 pub struct TagRegistry {
     tags: HashMap<String, ComponentId>,
 }
 
+impl TagRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, name: String, component_id: ComponentId) {
+        if let Some(old_id) = self.tags.insert(name.clone(), component_id) {
+            if old_id != component_id {
+                warn!(
+                    "Tag '{}' got registered twice, it now refer to another component",
+                    name
+                );
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<ComponentId, TagError> {
+        self.tags
+            .get(name)
+            .copied()
+            .ok_or_else(|| TagError::UnknownTag(name.to_string()))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.tags.contains_key(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagError {
+    /// The [`TagRegistry`] resource does not exist
+    NoRegistry,
+    UnknownTag(String),
+}
+
+impl Display for TagError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TagError::NoRegistry => write!(f, "the TagRegistry resource does not exist"),
+            TagError::UnknownTag(name) => {
+                write!(f, "the tag '{}' is not registered in the TagRegistry", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TagError {}
+
+/// Type data of the components registered as a tag automatically, under their type name in snake case
+/// ex: `#[derive(Component, Reflect)] #[reflect(Tag)] struct OnField;` can be referenced as "on_field"
+#[derive(Clone)]
+pub struct ReflectTag {
+    init_component: fn(&mut World) -> ComponentId,
+}
+
+impl<C: Component> FromType<C> for ReflectTag {
+    fn from_type() -> Self {
+        Self {
+            init_component: |world| world.init_component::<C>(),
+        }
+    }
+}
+
+pub trait TagAppExt {
+    /// Make the component usable in the queries of the card data under this name
+    fn register_tag<C: Component>(&mut self, name: &str) -> &mut Self;
+}
+
+impl TagAppExt for App {
+    fn register_tag<C: Component>(&mut self, name: &str) -> &mut Self {
+        let component_id = self.world_mut().init_component::<C>();
+
+        self.world_mut()
+            .get_resource_or_insert_with(TagRegistry::default)
+            .register(name.to_string(), component_id);
+        self
+    }
+}
+
+/// Register every type reflecting [`ReflectTag`], it runs before startup so every type got registered
+pub(crate) fn register_reflected_tags(world: &mut World) {
+    let tags: Vec<(String, ReflectTag)> = {
+        let type_registry = world.resource::<AppTypeRegistry>().read();
+
+        type_registry
+            .iter_with_data::<ReflectTag>()
+            .map(|(registration, tag)| {
+                let name = registration.type_info().type_path_table().short_path();
+                (to_snake_case(name), tag.clone())
+            })
+            .collect()
+    };
+
+    for (name, tag) in tags {
+        let component_id = (tag.init_component)(world);
+
+        world
+            .get_resource_or_insert_with(TagRegistry::default)
+            .register(name, component_id);
+    }
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+
+    for (index, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if index != 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
 pub trait RuntimeQueryExt {
     fn query_runtime<D: QueryData, F: QueryFilter>(
         &mut self,
         query_data: &[RunetimeQueryId],
     ) -> QueryBuilder<D, F>;
 
+    /// Fails instead of ignoring the tag when one of them is not registered
     fn query_runtime_tag<D: QueryData, F: QueryFilter>(
         &mut self,
         query_tags: &[RuntimeQueryTag],
-    ) -> QueryBuilder<D, F>;
+    ) -> Result<QueryBuilder<D, F>, TagError>;
 }
 
 impl RuntimeQueryExt for World {
@@ -42,21 +171,22 @@ impl RuntimeQueryExt for World {
     fn query_runtime_tag<D: QueryData, F: QueryFilter>(
         &mut self,
         query_tags: &[RuntimeQueryTag],
-    ) -> QueryBuilder<D, F> {
-        let mut ids = Vec::new();
-        let tag_registry = self.get_resource::<TagRegistry>().unwrap();
+    ) -> Result<QueryBuilder<D, F>, TagError> {
+        let tag_registry = self
+            .get_resource::<TagRegistry>()
+            .ok_or(TagError::NoRegistry)?;
 
-        for data in query_tags.iter() {
-            if let Ok(query_data) = data.to_ids(tag_registry) {
-                ids.push(query_data);
-            }
-        }
+        let ids = query_tags
+            .iter()
+            .map(|data| data.to_ids(tag_registry))
+            .collect::<Result<Vec<_>, _>>()?;
 
-        self.query_runtime::<D, F>(&ids)
+        Ok(self.query_runtime::<D, F>(&ids))
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "lowercase")]
 pub enum RuntimeQueryData<T> {
     With(Vec<T>),
     Without(Vec<T>),
@@ -95,20 +225,14 @@ impl RunetimeQueryId {
 }
 
 impl RuntimeQueryTag {
-    pub fn to_ids(&self, tag_registry: &TagRegistry) -> Result<RunetimeQueryId, String> {
+    pub fn to_ids(&self, tag_registry: &TagRegistry) -> Result<RunetimeQueryId, TagError> {
         match self {
             RuntimeQueryData::With(data) => {
-                let ids: Result<Vec<_>, _> = data.iter()
-                    .map(|d| tag_registry.tags.get(d)
-                        .ok_or_else(|| format!("Warning: Component '{}' not found in TagRegistry during RuntimeQueryData::to_ids", d)).copied())
-                    .collect();
+                let ids: Result<Vec<_>, _> = data.iter().map(|d| tag_registry.get(d)).collect();
                 Ok(RuntimeQueryData::With(ids?))
             }
             RuntimeQueryData::Without(data) => {
-                let ids: Result<Vec<_>, _> = data.iter()
-                    .map(|d| tag_registry.tags.get(d)
-                        .ok_or_else(|| format!("Warning: Component '{}' not found in TagRegistry during RuntimeQueryData::to_ids", d)).copied())
-                    .collect();
+                let ids: Result<Vec<_>, _> = data.iter().map(|d| tag_registry.get(d)).collect();
                 Ok(RuntimeQueryData::Without(ids?))
             }
             RuntimeQueryData::And(data) => {
@@ -122,3 +246,27 @@ impl RuntimeQueryTag {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type_names_are_converted_to_snake_case() {
+        assert_eq!(to_snake_case("Card"), "card");
+        assert_eq!(to_snake_case("OnField"), "on_field");
+        assert_eq!(to_snake_case("OnGraveyard"), "on_graveyard");
+    }
+
+    #[test]
+    fn lowercase_names_are_kept() {
+        assert_eq!(to_snake_case("card"), "card");
+        assert_eq!(to_snake_case("on_field"), "on_field");
+        assert_eq!(to_snake_case(""), "");
+    }
+
+    #[test]
+    fn every_uppercase_letter_starts_a_word() {
+        assert_eq!(to_snake_case("HP"), "h_p");
+    }
+}
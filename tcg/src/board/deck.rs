@@ -0,0 +1,40 @@
+#[cfg(feature = "render")]
+pub(crate) use cfg_deck_render::*;
+
+#[cfg(feature = "render")]
+mod cfg_deck_render {
+    use bevy::prelude::*;
+    use bevy_mod_picking::prelude::*;
+    use card_sim::{AgentDrawPacket, OnBoard};
+
+    use crate::card::CardAssets;
+
+    pub(crate) fn create_deck_render(
+        deck: In<Entity>,
+        mut commands: Commands,
+        card_assets: Res<CardAssets>,
+    ) {
+        commands
+            .entity(deck.0)
+            .insert(VisibilityBundle::default())
+            .with_children(|parent| {
+                parent.spawn(PbrBundle {
+                    mesh: card_assets.deck_mesh.clone(),
+                    material: card_assets.deck_material.clone(),
+                    ..default()
+                });
+            });
+
+        //TODO only on the client agent deck when the client know which agent he play
+        #[cfg(feature = "client")]
+        commands.entity(deck.0).insert(On::<Pointer<Click>>::run(
+            |event: Listener<Pointer<Click>>,
+             mut writer: EventWriter<AgentDrawPacket>,
+             on_boards: Query<&OnBoard>| {
+                if let Ok(on_board) = on_boards.get(event.listener()) {
+                    writer.send(AgentDrawPacket::new(on_board.0));
+                }
+            },
+        ));
+    }
+}
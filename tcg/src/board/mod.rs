@@ -1,22 +1,25 @@
 pub mod client_action;
+mod deck;
 mod hand;
 mod slot;
 
 use epithet::units::UnitPluginExt;
 
-use card_sim::BoardSlot;
+use card_sim::{BoardDeck, BoardSlot};
 
 #[cfg(feature = "render")]
 pub use cgf_board_mod_render::*;
 
 pub(crate) fn board_plugin(app: &mut bevy::app::App) {
     app.add_unit::<BoardSlot>();
+    app.add_unit::<BoardDeck>();
 
     #[cfg(feature = "render")]
     {
         use bevy::prelude::*;
         use card_sim::player_joined_packet_system;
         use client_action::board_action_plugin;
+        use deck::create_deck_render;
         use hand::on_client_join_board_render;
         use hand::remove_from_hand_observer;
         use slot::create_slot_render;
@@ -32,6 +35,10 @@ pub(crate) fn board_plugin(app: &mut bevy::app::App) {
 
         app.bind_render::<BoardSlot>(id);
 
+        let id = app.register_system(create_deck_render);
+
+        app.bind_render::<BoardDeck>(id);
+
         app.add_systems(
             Update,
             on_client_join_board_render.after(player_joined_packet_system), //TODO transform is on server side client to bruh
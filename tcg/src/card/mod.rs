@@ -1,5 +1,8 @@
 mod render;
 
+#[cfg(feature = "render")]
+pub(crate) use render::CardAssets;
+
 use card_sim::Card;
 use epithet::units::UnitPluginExt;
 
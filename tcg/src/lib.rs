@@ -11,7 +11,7 @@ use bevy_mod_picking::DefaultPickingPlugins;
 use bevy_replicon::prelude::AppRuleExt;
 use board::board_plugin;
 use card::card_plugin;
-use card_sim::CardSimPlugin;
+use card_sim::{CardPluginExt, CardSimPlugin};
 use epithet::{net::NetPlugins, units::UnitPlugin};
 use scene::dev_room_plugin;
 use state::state_plugin;
@@ -23,6 +23,9 @@ mod scene;
 mod state;
 mod ui;
 
+/// The card data files folder, relative to the `assets` folder of the crate
+pub const CARD_ASSETS_FOLDER: &str = "cards";
+
 pub fn create_app<S: Into<String>>(window_name: S) {
     let mut app = App::new();
 
@@ -47,6 +50,8 @@ pub fn create_app<S: Into<String>>(window_name: S) {
         dev_room_plugin,
     ));
 
+    app.load_card_assets(CARD_ASSETS_FOLDER);
+
     app.add_systems(Update, inspector_ui);
 
     app.insert_resource(WinitSettings {
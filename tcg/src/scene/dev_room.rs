@@ -3,8 +3,8 @@ use bevy_mod_picking::prelude::*;
 use bevy_replicon::core::Replicated;
 use card_sim::{
     AgentOwned, Board, BoardAgentJoin, BoardStage, Card, CardAttribute, CardBundle, CardId,
-    CardVisibility, ClientJoinBoardRequestPacket, ClientJoinedBoardPacket, OnBoard, OnHand,
-    StageChangePacket, CARD_HEIGHT, CARD_WIDTH,
+    CardVisibility, ClientJoinBoardRequestPacket, ClientJoinedBoardPacket, ClientTurnStarted, Deck,
+    OnBoard, OnHand, StageChangePacket, CARD_HEIGHT, CARD_WIDTH,
 };
 use epithet::{
     agent::AgentManager,
@@ -18,11 +18,13 @@ pub(crate) fn dev_room_plugin(app: &mut App) {
     app.add_systems(Update, on_client_joined_board_dev_room_scene);
 
     app.observe(on_board_agent_join);
+    app.observe(on_client_turn_started);
 }
 
 pub fn on_board_agent_join(
     trigger: Trigger<BoardAgentJoin>,
     mut boards: Query<&mut Board>,
+    mut decks: Query<&mut Deck>,
     mut commands: Commands,
     unit_registry: Res<UnitRegistry>,
     auth_manager: Res<AuthManager>,
@@ -53,6 +55,13 @@ pub fn on_board_agent_join(
                 AgentOwned(trigger.event().agent),
             ));
         }
+
+        if let Some(deck_entity) = board.cache.get_deck(&trigger.event().agent).copied() {
+            if let Ok(mut deck) = decks.get_mut(deck_entity) {
+                *deck = Deck::new((0..20).map(|i| CardId(i % 2)).collect());
+                deck.shuffle(board.state.rng());
+            }
+        }
     } else {
         error!(
             "Board {:?} on agent join, board not found, this should be a impossible state",
@@ -61,6 +70,10 @@ pub fn on_board_agent_join(
     }
 }
 
+pub fn on_client_turn_started(trigger: Trigger<ClientTurnStarted>) {
+    info!("Your turn started (turn {})", trigger.event().turn);
+}
+
 //RepliconObserver
 pub fn on_client_joined_board_dev_room_scene(
     mut commands: Commands,
@@ -116,10 +129,11 @@ pub fn create_dev_room_core_scene(mut commands: Commands) {
 }
 
 pub fn create_dev_room_scene(mut commands: Commands) {
-    commands.spawn((
-        Board::new(vec![]),
-        Replicated,
-        LevelEntity,
-        Name::new("Board"),
-    ));
+    let board = Board::new(vec![]);
+
+    info!(
+        "Dev room board created with the seed {}",
+        board.state.seed()
+    );
+    commands.spawn((board, Replicated, LevelEntity, Name::new("Board")));
 }